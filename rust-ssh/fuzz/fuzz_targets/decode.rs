@@ -0,0 +1,17 @@
+//! Feed arbitrary bytes to the decoders, which must return errors rather than panic
+//!
+//! Run with `cargo fuzz run decode` from the `rust-ssh` directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_ssh::identification::Identification;
+use rust_ssh::message::decode_any_message;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_any_message(data);
+
+    if let Ok(identification_string) = std::str::from_utf8(data) {
+        let _ = Identification::decode_from_string(identification_string.into());
+    }
+});
@@ -0,0 +1,507 @@
+//! Encoding and decoding of the binary data types defined by RFC 4251 section 5
+
+use crate::message::{DecodeError, EncodeError, NameList};
+use crate::prelude::*;
+
+use core::borrow::BorrowMut;
+
+/// Encode a non-negative integer given as big endian bytes as an `mpint`
+///
+/// Leading zero bytes are removed and a single zero byte is added back when the high bit is set
+/// so that the value isn't read as negative. Zero is encoded as an empty string.
+pub fn encode_mpint(value: &[u8]) -> Vec<u8> {
+    let start = value.iter().position(|&x| x != 0).unwrap_or(value.len());
+    let value = &value[start..];
+
+    let mut encoded = vec![];
+    match value.first() {
+        Some(&first) if first & 0x80 != 0 => {
+            encoded.extend((value.len() as u32 + 1).to_be_bytes());
+            encoded.push(0);
+        }
+        _ => encoded.extend((value.len() as u32).to_be_bytes()),
+    }
+    encoded.extend(value);
+
+    encoded
+}
+
+/// Encode a signed integer given as big endian two's complement bytes as an `mpint`
+///
+/// Redundant leading `0x00` and `0xff` bytes are removed, as RFC 4251 doesn't allow them.
+pub fn encode_signed_mpint(value: &[u8]) -> Vec<u8> {
+    let mut value = value;
+    while let [first, second, ..] = value {
+        match (first, second & 0x80) {
+            (0x00, 0) | (0xff, 0x80) => value = &value[1..],
+            _ => break,
+        }
+    }
+    if value == [0] {
+        value = &[];
+    }
+
+    let mut encoded = (value.len() as u32).to_be_bytes().to_vec();
+    encoded.extend(value);
+
+    encoded
+}
+
+/// Decode an `mpint` from the start of `data`
+///
+/// Returns the big endian two's complement bytes of the value, exactly as they were sent, along
+/// with the number of bytes consumed from `data`. Values with unnecessary leading bytes are
+/// rejected.
+pub fn decode_mpint(data: &[u8]) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut reader = Reader::new(data);
+    let value = reader.get_mpint()?;
+
+    Ok((value, reader.position()))
+}
+
+/// Builds up an encoded message one field at a time
+///
+/// The message is written into a new buffer by default, or appended to an existing one with
+/// `append_to`.
+#[derive(Default)]
+pub struct Writer<B: BorrowMut<Vec<u8>> = Vec<u8>> {
+    buffer: B,
+    /// Where this message starts in the buffer
+    start: usize,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Finish writing and return the encoded data
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+impl<'a> Writer<&'a mut Vec<u8>> {
+    /// Append to the end of `buffer` rather than allocating a new one
+    pub fn append_to(buffer: &'a mut Vec<u8>) -> Self {
+        let start = buffer.len();
+
+        Self { buffer, start }
+    }
+}
+
+impl<B: BorrowMut<Vec<u8>>> Writer<B> {
+    /// Append a single `byte`
+    pub fn put_u8(&mut self, value: u8) {
+        self.buffer().push(value);
+    }
+
+    /// Append raw bytes without a length prefix, for fixed size fields like the kex cookie
+    pub fn put_bytes(&mut self, value: &[u8]) {
+        self.buffer().extend(value);
+    }
+
+    /// Append a big endian `uint32`
+    pub fn put_u32(&mut self, value: u32) {
+        self.buffer().extend(value.to_be_bytes());
+    }
+
+    /// Append a big endian `uint64`
+    pub fn put_u64(&mut self, value: u64) {
+        self.buffer().extend(value.to_be_bytes());
+    }
+
+    /// Append a `boolean`, which is always sent as 0 or 1
+    pub fn put_bool(&mut self, value: bool) {
+        self.buffer().push(value as u8);
+    }
+
+    /// Append a length prefixed `string`
+    pub fn put_string(&mut self, value: &[u8]) {
+        self.put_u32(value.len() as u32);
+        self.buffer().extend(value);
+    }
+
+    /// Append a non-negative `mpint` given as big endian bytes
+    pub fn put_mpint(&mut self, value: &[u8]) {
+        self.buffer().extend(encode_mpint(value));
+    }
+
+    /// Append a `name-list` of comma separated names
+    ///
+    /// Names are required to be US-ASCII by RFC 4251, so any name that isn't is rejected rather
+    /// than being silently truncated to bytes. Names containing a comma are rejected as well.
+    pub fn put_name_list<T: core::fmt::Display>(
+        &mut self,
+        value: &NameList<T>,
+    ) -> Result<(), EncodeError> {
+        // Report the name that caused the problem rather than the whole list
+        for name in value {
+            let name = name.to_string();
+
+            if !name.is_ascii() {
+                return Err(EncodeError::NonAsciiName { name });
+            }
+            // The peer would split the name in two when decoding the list
+            if name.contains(',') {
+                return Err(EncodeError::CommaInName { name });
+            }
+        }
+
+        self.put_string(value.to_string().as_bytes());
+
+        Ok(())
+    }
+
+    /// The number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.buffer.borrow().len() - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn buffer(&mut self) -> &mut Vec<u8> {
+        self.buffer.borrow_mut()
+    }
+}
+
+/// Reads fields from the start of an encoded message, checking that each one is complete
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// The data that hasn't been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position..]
+    }
+
+    /// The number of bytes read so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Read `count` raw bytes
+    pub fn get_bytes(&mut self, count: usize) -> Result<&'a [u8], DecodeError> {
+        let remaining = self.remaining();
+
+        if remaining.len() < count {
+            return Err(DecodeError::UnexpectedEnd {
+                needed: count,
+                available: remaining.len(),
+            });
+        }
+
+        self.position += count;
+
+        Ok(&remaining[..count])
+    }
+
+    /// Read a single `byte`
+    pub fn get_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.get_bytes(1)?[0])
+    }
+
+    /// Read a big endian `uint32`
+    pub fn get_u32(&mut self) -> Result<u32, DecodeError> {
+        let value = self.get_bytes(4)?;
+
+        Ok(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    /// Read a big endian `uint64`
+    pub fn get_u64(&mut self) -> Result<u64, DecodeError> {
+        let mut value = [0; 8];
+        value.copy_from_slice(self.get_bytes(8)?);
+
+        Ok(u64::from_be_bytes(value))
+    }
+
+    /// Read a `boolean`
+    ///
+    /// Any non-zero value is treated as true as required by RFC 4251
+    pub fn get_bool(&mut self) -> Result<bool, DecodeError> {
+        Ok(self.get_u8()? != 0)
+    }
+
+    /// Read a length prefixed `string`
+    pub fn get_string(&mut self) -> Result<&'a [u8], DecodeError> {
+        let start = self.position;
+        let length = self.get_u32()? as usize;
+
+        match self.get_bytes(length) {
+            Ok(value) => Ok(value),
+            Err(_) => {
+                let available = self.data.len() - start;
+                self.position = start;
+
+                Err(DecodeError::UnexpectedEnd {
+                    needed: length.saturating_add(4),
+                    available,
+                })
+            }
+        }
+    }
+
+    /// Read a length prefixed UTF-8 `string`
+    pub fn get_utf8(&mut self) -> Result<String, DecodeError> {
+        match core::str::from_utf8(self.get_string()?) {
+            Ok(value) => Ok(value.into()),
+            Err(_) => Err(DecodeError::InvalidUtf8),
+        }
+    }
+
+    /// Read an `mpint`, returning its big endian two's complement bytes as they were sent
+    pub fn get_mpint(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let start = self.position;
+        let value = self.get_string()?;
+
+        let redundant = match value {
+            [0] => true,
+            [first, second, ..] => matches!((first, second & 0x80), (0x00, 0) | (0xff, 0x80)),
+            _ => false,
+        };
+
+        if redundant {
+            self.position = start;
+            return Err(DecodeError::NonMinimalMpint);
+        }
+
+        Ok(value.to_vec())
+    }
+
+    /// Read an `mpint` that must not be negative, returning its big endian bytes without the
+    /// leading zero byte that keeps the high bit clear
+    pub fn get_unsigned_mpint(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let start = self.position;
+        let mut value = self.get_mpint()?;
+
+        match value.first() {
+            Some(&first) if first & 0x80 != 0 => {
+                self.position = start;
+                Err(DecodeError::NegativeMpint)
+            }
+            Some(0) => {
+                value.remove(0);
+                Ok(value)
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Read a `name-list`
+    ///
+    /// An empty string decodes to an empty list, but empty names inside of a list are rejected.
+    pub fn get_name_list(&mut self) -> Result<NameList<String>, DecodeError> {
+        let list = self.get_string()?;
+
+        if !list.is_ascii() {
+            return Err(DecodeError::NonAsciiName {
+                list: String::from_utf8_lossy(list).into(),
+            });
+        }
+
+        // Every byte is ASCII so this can't fail
+        let list = String::from_utf8(list.to_vec()).unwrap();
+
+        if list.is_empty() {
+            return Ok(vec![].into());
+        }
+
+        if list.split(',').any(|name| name.is_empty()) {
+            return Err(DecodeError::EmptyName { list });
+        }
+
+        Ok(list.split(',').map(String::from).collect::<Vec<_>>().into())
+    }
+
+    /// Check that all of the data has been read
+    pub fn expect_end(&self) -> Result<(), DecodeError> {
+        match self.remaining().len() {
+            0 => Ok(()),
+            count => Err(DecodeError::TrailingBytes { count }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::*;
+
+    #[test]
+    fn writer_round_trip() {
+        let mut writer = Writer::new();
+        writer.put_u8(20);
+        writer.put_u32(0x01020304);
+        writer.put_u64(0x0102030405060708);
+        writer.put_bool(true);
+        writer.put_string(b"ssh-userauth");
+        writer
+            .put_name_list(&NameList::from(vec!["zlib", "none"]))
+            .unwrap();
+
+        let encoded = writer.into_bytes();
+        let mut reader = Reader::new(&encoded);
+
+        assert_eq!(reader.get_u8(), Ok(20));
+        assert_eq!(reader.get_u32(), Ok(0x01020304));
+        assert_eq!(reader.get_u64(), Ok(0x0102030405060708));
+        assert_eq!(reader.get_bool(), Ok(true));
+        assert_eq!(reader.get_string(), Ok(&b"ssh-userauth"[..]));
+        assert_eq!(reader.get_name_list().unwrap().to_string(), "zlib,none");
+        assert_eq!(reader.expect_end(), Ok(()));
+    }
+
+    #[test]
+    fn get_bool_treats_nonzero_as_true() {
+        let mut reader = Reader::new(&[0x00, 0x01, 0x02, 0xff]);
+
+        assert_eq!(reader.get_bool(), Ok(false));
+        assert_eq!(reader.get_bool(), Ok(true));
+        assert_eq!(reader.get_bool(), Ok(true));
+        assert_eq!(reader.get_bool(), Ok(true));
+    }
+
+    #[test]
+    fn integers_are_big_endian() {
+        let mut writer = Writer::new();
+        writer.put_u32(0x0a0b0c0d);
+
+        assert_eq!(writer.into_bytes(), [0x0a, 0x0b, 0x0c, 0x0d]);
+    }
+
+    #[test]
+    fn put_mpint_padding() {
+        for (value, encoded) in [
+            (&[][..], &b"\0\0\0\0"[..]),
+            (&[0, 0], &b"\0\0\0\0"[..]),
+            (&[0x7f], &b"\0\0\0\x01\x7f"[..]),
+            (&[0x80], &b"\0\0\0\x02\0\x80"[..]),
+            (&[0, 0x12, 0x34], &b"\0\0\0\x02\x12\x34"[..]),
+        ] {
+            let mut writer = Writer::new();
+            writer.put_mpint(value);
+
+            assert_eq!(writer.into_bytes(), encoded);
+        }
+    }
+
+    #[test]
+    fn mpint_rfc_4251_examples() {
+        let examples: [(&[u8], &[u8]); 5] = [
+            (&[], b"\0\0\0\0"),
+            (
+                &[0x09, 0xa3, 0x78, 0xf9, 0xb2, 0xe3, 0x32, 0xa7],
+                b"\0\0\0\x08\x09\xa3\x78\xf9\xb2\xe3\x32\xa7",
+            ),
+            (&[0x00, 0x80], b"\0\0\0\x02\0\x80"),
+            // -1234
+            (&[0xed, 0xcc], b"\0\0\0\x02\xed\xcc"),
+            // -deadbeef
+            (
+                &[0xff, 0x21, 0x52, 0x41, 0x11],
+                b"\0\0\0\x05\xff\x21\x52\x41\x11",
+            ),
+        ];
+
+        for (value, encoded) in examples {
+            assert_eq!(encode_signed_mpint(value), encoded);
+            assert_eq!(decode_mpint(encoded), Ok((value.to_vec(), encoded.len())));
+        }
+
+        // Unsigned values get the leading zero added when they need it
+        assert_eq!(encode_mpint(&[0x80]), b"\0\0\0\x02\0\x80");
+        assert_eq!(encode_mpint(&[0, 0, 0x7f]), b"\0\0\0\x01\x7f");
+        assert_eq!(encode_mpint(&[0, 0]), b"\0\0\0\0");
+    }
+
+    #[test]
+    fn signed_mpint_removes_redundant_bytes() {
+        assert_eq!(encode_signed_mpint(&[0, 0]), b"\0\0\0\0");
+        assert_eq!(encode_signed_mpint(&[0, 0, 0x80]), b"\0\0\0\x02\0\x80");
+        assert_eq!(encode_signed_mpint(&[0xff, 0xff]), b"\0\0\0\x01\xff");
+        assert_eq!(encode_signed_mpint(&[0xff, 0x7f]), b"\0\0\0\x02\xff\x7f");
+    }
+
+    #[test]
+    fn decode_mpint_rejects_redundant_bytes() {
+        for encoded in [
+            &b"\0\0\0\x01\0"[..],
+            b"\0\0\0\x02\0\x7f",
+            b"\0\0\0\x02\xff\x80",
+        ] {
+            assert_eq!(decode_mpint(encoded), Err(DecodeError::NonMinimalMpint));
+        }
+    }
+
+    #[test]
+    fn unsigned_mpints() {
+        let mut reader = Reader::new(b"\0\0\0\x02\0\x80\0\0\0\x01\x7f\0\0\0\0\0\0\0\x01\x80");
+
+        assert_eq!(reader.get_unsigned_mpint(), Ok(vec![0x80]));
+        assert_eq!(reader.get_unsigned_mpint(), Ok(vec![0x7f]));
+        assert_eq!(reader.get_unsigned_mpint(), Ok(vec![]));
+        assert_eq!(reader.get_unsigned_mpint(), Err(DecodeError::NegativeMpint));
+        assert_eq!(reader.remaining(), b"\0\0\0\x01\x80");
+    }
+
+    #[test]
+    fn reader_errors() {
+        let mut reader = Reader::new(b"\0\0\0\x05abc");
+
+        assert_eq!(
+            reader.get_string(),
+            Err(DecodeError::UnexpectedEnd {
+                needed: 9,
+                available: 7
+            })
+        );
+
+        // A failed read leaves the reader where it was
+        assert_eq!(reader.get_u32(), Ok(5));
+        assert_eq!(
+            reader.expect_end(),
+            Err(DecodeError::TrailingBytes { count: 3 })
+        );
+        assert_eq!(
+            reader.get_u64(),
+            Err(DecodeError::UnexpectedEnd {
+                needed: 8,
+                available: 3
+            })
+        );
+    }
+
+    #[test]
+    fn string_with_largest_length() {
+        let mut reader = Reader::new(b"\xff\xff\xff\xffabc");
+
+        assert_eq!(
+            reader.get_string(),
+            Err(DecodeError::UnexpectedEnd {
+                needed: (u32::MAX as usize).saturating_add(4),
+                available: 7
+            })
+        );
+    }
+
+    #[test]
+    fn writer_appends_to_buffer() {
+        let mut buffer = b"head".to_vec();
+
+        let mut writer = Writer::append_to(&mut buffer);
+        assert!(writer.is_empty());
+        writer.put_u32(1);
+        writer.put_string(b"ab");
+        assert_eq!(writer.len(), 10);
+
+        assert_eq!(buffer, b"head\0\0\0\x01\0\0\0\x02ab");
+    }
+}
@@ -0,0 +1,27 @@
+use crate::prelude::*;
+
+/// Possible errors when compressing or decompressing packet payloads
+#[derive(Debug, Eq, PartialEq)]
+pub enum CompressionError {
+    /// There is no implementation of the compression algorithm available
+    Unsupported { algorithm: String },
+    /// The payload couldn't be compressed
+    CompressionFailed,
+    /// The received payload wasn't valid compressed data, or decompressed to more than the
+    /// maximum packet length
+    DecompressionFailed,
+}
+
+impl core::fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Unsupported { algorithm } => {
+                write!(f, "The {algorithm} compression algorithm is not supported")
+            }
+            Self::CompressionFailed => write!(f, "Failed to compress the payload"),
+            Self::DecompressionFailed => write!(f, "Failed to decompress the payload"),
+        }
+    }
+}
+
+impl core::error::Error for CompressionError {}
@@ -0,0 +1,177 @@
+//! Compression of packet payloads, as negotiated in a `KexInitMessage`
+
+mod compression_error;
+
+pub use compression_error::*;
+
+use crate::message::CompressionAlgorithm;
+use crate::prelude::*;
+
+/// The compression algorithms that have a working implementation, which `Compression::new`
+/// accepts
+///
+/// `zstd@openssh.com` is only included with the `zstd` feature.
+pub fn supported_compression() -> Vec<CompressionAlgorithm> {
+    vec![
+        #[cfg(feature = "zstd")]
+        CompressionAlgorithm::Zstd,
+        CompressionAlgorithm::None,
+    ]
+}
+
+/// The negotiated compression for one direction of a connection
+///
+/// Algorithms with an `@openssh.com` suffix are delayed: they only start compressing once user
+/// authentication has succeeded, which the transport signals with `set_authenticated`. Each
+/// payload is compressed on its own, so packets can be decoded in any order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Compression {
+    algorithm: CompressionAlgorithm,
+    delayed: bool,
+    authenticated: bool,
+}
+
+impl Compression {
+    /// No compression, which is what every connection starts with
+    pub fn none() -> Self {
+        Self {
+            algorithm: CompressionAlgorithm::None,
+            delayed: false,
+            authenticated: false,
+        }
+    }
+
+    /// Compression with a negotiated algorithm
+    ///
+    /// Returns an error if the algorithm has no implementation, `zstd@openssh.com` needs the
+    /// `zstd` feature.
+    pub fn new(algorithm: CompressionAlgorithm) -> Result<Self, CompressionError> {
+        match algorithm {
+            CompressionAlgorithm::None => Ok(Self::none()),
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => Ok(Self {
+                algorithm,
+                delayed: true,
+                authenticated: false,
+            }),
+            algorithm => Err(CompressionError::Unsupported {
+                algorithm: algorithm.to_string(),
+            }),
+        }
+    }
+
+    /// The algorithm that was negotiated
+    pub fn algorithm(&self) -> &CompressionAlgorithm {
+        &self.algorithm
+    }
+
+    /// Record that user authentication succeeded, which starts delayed compression
+    pub fn set_authenticated(&mut self) {
+        self.authenticated = true;
+    }
+
+    /// Whether payloads are currently being compressed
+    pub fn is_active(&self) -> bool {
+        self.algorithm != CompressionAlgorithm::None && (!self.delayed || self.authenticated)
+    }
+
+    /// Compress a payload before it is put in a packet, it is returned unchanged while
+    /// compression isn't active
+    pub fn compress(&self, payload: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        if !self.is_active() {
+            return Ok(payload.to_vec());
+        }
+
+        match self.algorithm {
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => {
+                zstd::bulk::compress(payload, 0).map_err(|_| CompressionError::CompressionFailed)
+            }
+            _ => Err(CompressionError::Unsupported {
+                algorithm: self.algorithm.to_string(),
+            }),
+        }
+    }
+
+    /// Decompress the payload of a received packet, it is returned unchanged while compression
+    /// isn't active
+    ///
+    /// Payloads that would decompress to more than `MAX_PACKET_LENGTH` are rejected, so that a
+    /// small packet can't be used to make us allocate a huge amount of memory.
+    pub fn decompress(&self, payload: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        if !self.is_active() {
+            return Ok(payload.to_vec());
+        }
+
+        match self.algorithm {
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => {
+                zstd::bulk::decompress(payload, crate::packet::MAX_PACKET_LENGTH)
+                    .map_err(|_| CompressionError::DecompressionFailed)
+            }
+            _ => Err(CompressionError::Unsupported {
+                algorithm: self.algorithm.to_string(),
+            }),
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compression::*;
+
+    #[test]
+    fn supported_compression_instantiates() {
+        for algorithm in supported_compression() {
+            assert_eq!(
+                Compression::new(algorithm.clone()).unwrap().algorithm(),
+                &algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn none_leaves_payloads_unchanged() {
+        let compression = Compression::none();
+
+        assert!(!compression.is_active());
+        assert_eq!(compression.compress(b"payload"), Ok(b"payload".to_vec()));
+        assert_eq!(compression.decompress(b"payload"), Ok(b"payload".to_vec()));
+    }
+
+    #[test]
+    fn unknown_algorithms_are_unsupported() {
+        assert_eq!(
+            Compression::new(CompressionAlgorithm::Unknown("zlib".into())),
+            Err(CompressionError::Unsupported {
+                algorithm: "zlib".into()
+            })
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_is_delayed_until_authenticated() {
+        let mut compression = Compression::new(CompressionAlgorithm::Zstd).unwrap();
+        let payload = vec![b'a'; 1000];
+
+        assert!(!compression.is_active());
+        assert_eq!(compression.compress(&payload), Ok(payload.clone()));
+
+        compression.set_authenticated();
+        let compressed = compression.compress(&payload).unwrap();
+
+        assert!(compressed.len() < payload.len());
+        assert_eq!(compression.decompress(&compressed), Ok(payload));
+        assert_eq!(
+            compression.decompress(b"not zstd"),
+            Err(CompressionError::DecompressionFailed)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use super::{Cipher, CipherError};
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use aes::{Aes128, Aes192, Aes256};
+use zeroize::ZeroizeOnDrop;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes192Ctr = ctr::Ctr128BE<Aes192>;
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// AES in counter mode as defined by RFC 4344, the key length decides which AES variant is used
+///
+/// The expanded key and counter are cleared from memory when the cipher is dropped.
+pub enum AesCtr {
+    Aes128(Aes128Ctr),
+    Aes192(Aes192Ctr),
+    Aes256(Aes256Ctr),
+}
+
+// Every variant zeroizes itself on drop, so the enum as a whole does too
+impl ZeroizeOnDrop for AesCtr
+where
+    Aes128Ctr: ZeroizeOnDrop,
+    Aes192Ctr: ZeroizeOnDrop,
+    Aes256Ctr: ZeroizeOnDrop,
+{
+}
+
+impl core::fmt::Debug for AesCtr {
+    /// Only shows the key size, the cipher state includes the expanded key
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let variant = match self {
+            Self::Aes128(_) => "Aes128",
+            Self::Aes192(_) => "Aes192",
+            Self::Aes256(_) => "Aes256",
+        };
+
+        f.debug_tuple("AesCtr")
+            .field(&format_args!("{variant}"))
+            .finish()
+    }
+}
+
+impl AesCtr {
+    /// Create a new cipher from a 16, 24 or 32 byte key and a 16 byte initial counter block
+    pub fn new(key: &[u8], iv: &[u8]) -> Result<Self, CipherError> {
+        let invalid_length = |_| CipherError::InvalidKeyLength {
+            key_len: key.len(),
+            iv_len: iv.len(),
+        };
+
+        Ok(match key.len() {
+            16 => Self::Aes128(Aes128Ctr::new_from_slices(key, iv).map_err(invalid_length)?),
+            24 => Self::Aes192(Aes192Ctr::new_from_slices(key, iv).map_err(invalid_length)?),
+            32 => Self::Aes256(Aes256Ctr::new_from_slices(key, iv).map_err(invalid_length)?),
+            _ => {
+                return Err(CipherError::InvalidKeyLength {
+                    key_len: key.len(),
+                    iv_len: iv.len(),
+                })
+            }
+        })
+    }
+
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        match self {
+            Self::Aes128(cipher) => cipher.apply_keystream(data),
+            Self::Aes192(cipher) => cipher.apply_keystream(data),
+            Self::Aes256(cipher) => cipher.apply_keystream(data),
+        }
+    }
+}
+
+impl Cipher for AesCtr {
+    fn name(&self) -> &str {
+        match self {
+            Self::Aes128(_) => "aes128-ctr",
+            Self::Aes192(_) => "aes192-ctr",
+            Self::Aes256(_) => "aes256-ctr",
+        }
+    }
+
+    fn get_block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), CipherError> {
+        self.apply_keystream(data);
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), CipherError> {
+        self.apply_keystream(data);
+        Ok(())
+    }
+}
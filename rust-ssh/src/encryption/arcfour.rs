@@ -0,0 +1,168 @@
+use super::{Cipher, CipherError};
+
+use rc4::consts::{U16, U32};
+use rc4::{KeyInit, Rc4, StreamCipher};
+use zeroize::ZeroizeOnDrop;
+
+/// The number of keystream bytes the RFC 4345 variants throw away after keying
+const DISCARD_LEN: usize = 1536;
+
+/// The RC4 stream cipher, for servers that offer nothing better
+///
+/// RC4 is cryptographically weak: its keystream has well known biases, and RFC 8758 deprecates
+/// it for SSH. It is only available with the `legacy-ciphers` feature.
+///
+/// `arcfour` from RFC 4253 uses the keystream from the start. `arcfour128` and `arcfour256` from
+/// RFC 4345 discard the first 1536 bytes, which hides the worst of the biases.
+pub struct ArcFour {
+    name: &'static str,
+    state: Rc4State,
+}
+
+enum Rc4State {
+    Key128(Rc4<U16>),
+    Key256(Rc4<U32>),
+}
+
+// Both key sizes zeroize themselves on drop, so the cipher as a whole does too
+impl ZeroizeOnDrop for ArcFour
+where
+    Rc4<U16>: ZeroizeOnDrop,
+    Rc4<U32>: ZeroizeOnDrop,
+{
+}
+
+impl core::fmt::Debug for ArcFour {
+    /// Only shows the name, the cipher state is derived from the key
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_tuple("ArcFour")
+            .field(&format_args!("{}", self.name))
+            .finish()
+    }
+}
+
+impl ArcFour {
+    /// Create `arcfour` from a 16 byte key, without discarding any keystream
+    pub fn new(key: &[u8]) -> Result<Self, CipherError> {
+        match key.len() {
+            16 => Ok(Self {
+                name: "arcfour",
+                state: Rc4State::Key128(Rc4::new(key.into())),
+            }),
+            _ => Err(CipherError::InvalidKeyLength {
+                key_len: key.len(),
+                iv_len: 0,
+            }),
+        }
+    }
+
+    /// Create `arcfour128` or `arcfour256` from a 16 or 32 byte key, discarding the first 1536
+    /// bytes of keystream
+    pub fn new_discarding(key: &[u8]) -> Result<Self, CipherError> {
+        let mut cipher = match key.len() {
+            16 => Self {
+                name: "arcfour128",
+                state: Rc4State::Key128(Rc4::new(key.into())),
+            },
+            32 => Self {
+                name: "arcfour256",
+                state: Rc4State::Key256(Rc4::new(key.into())),
+            },
+            _ => {
+                return Err(CipherError::InvalidKeyLength {
+                    key_len: key.len(),
+                    iv_len: 0,
+                })
+            }
+        };
+
+        cipher.apply_keystream(&mut [0; DISCARD_LEN]);
+
+        Ok(cipher)
+    }
+
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        match &mut self.state {
+            Rc4State::Key128(cipher) => cipher.apply_keystream(data),
+            Rc4State::Key256(cipher) => cipher.apply_keystream(data),
+        }
+    }
+}
+
+impl Cipher for ArcFour {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn get_block_size(&self) -> usize {
+        1
+    }
+
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), CipherError> {
+        self.apply_keystream(data);
+        Ok(())
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), CipherError> {
+        self.apply_keystream(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encryption::*;
+
+    /// Encrypting zeroes gives the keystream
+    fn keystream(cipher: &mut ArcFour, len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        cipher.encrypt(&mut data).unwrap();
+
+        data
+    }
+
+    #[test]
+    fn known_answers() {
+        // RFC 6229 test vectors for the keys 0x0102..10 and 0x0102..20, at offsets 0 and 1536
+        let key_128: Vec<u8> = (1..=16).collect();
+        let key_256: Vec<u8> = (1..=32).collect();
+
+        assert_eq!(
+            keystream(&mut ArcFour::new(&key_128).unwrap(), 16),
+            [
+                0x9a, 0xc7, 0xcc, 0x9a, 0x60, 0x9d, 0x1e, 0xf7, 0xb2, 0x93, 0x28, 0x99, 0xcd, 0xe4,
+                0x1b, 0x97,
+            ]
+        );
+        assert_eq!(
+            keystream(&mut ArcFour::new_discarding(&key_128).unwrap(), 16),
+            [
+                0xff, 0xa0, 0xb5, 0x14, 0x64, 0x7e, 0xc0, 0x4f, 0x63, 0x06, 0xb8, 0x92, 0xae, 0x66,
+                0x11, 0x81,
+            ]
+        );
+        assert_eq!(
+            keystream(&mut ArcFour::new_discarding(&key_256).unwrap(), 16),
+            [
+                0x3e, 0x34, 0x13, 0x5c, 0x79, 0xdb, 0x01, 0x02, 0x00, 0x76, 0x76, 0x51, 0xcf, 0x26,
+                0x30, 0x73,
+            ]
+        );
+    }
+
+    #[test]
+    fn names_and_key_lengths() {
+        assert_eq!(ArcFour::new(&[1; 16]).unwrap().name(), "arcfour");
+        assert_eq!(
+            ArcFour::new_discarding(&[1; 16]).unwrap().name(),
+            "arcfour128"
+        );
+        assert_eq!(
+            ArcFour::new_discarding(&[1; 32]).unwrap().name(),
+            "arcfour256"
+        );
+
+        assert!(ArcFour::new(&[1; 32]).is_err());
+        assert!(ArcFour::new_discarding(&[1; 24]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use super::{AeadCipher, Cipher, CipherError};
+use crate::prelude::*;
+
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20Legacy;
+use poly1305::universal_hash::KeyInit;
+use poly1305::Poly1305;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// The `chacha20-poly1305@openssh.com` cipher as specified in OpenSSH's PROTOCOL.chacha20poly1305
+///
+/// The 64 byte key is split into two ChaCha20 keys, the second half encrypts the packet length
+/// and the first half encrypts the rest of the packet and generates the Poly1305 key. The packet
+/// sequence number is used as the nonce. Both keys are cleared from memory when dropped.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ChaCha20Poly1305 {
+    main_key: [u8; 32],
+    header_key: [u8; 32],
+}
+
+impl core::fmt::Debug for ChaCha20Poly1305 {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ChaCha20Poly1305").finish_non_exhaustive()
+    }
+}
+
+impl ChaCha20Poly1305 {
+    pub const KEY_LEN: usize = 64;
+    pub const TAG_LEN: usize = 16;
+
+    pub fn new(key: &[u8]) -> Result<Self, CipherError> {
+        if key.len() != Self::KEY_LEN {
+            return Err(CipherError::InvalidKeyLength {
+                key_len: key.len(),
+                iv_len: 0,
+            });
+        }
+
+        let mut cipher = Self {
+            main_key: [0; 32],
+            header_key: [0; 32],
+        };
+        cipher.main_key.copy_from_slice(&key[..32]);
+        cipher.header_key.copy_from_slice(&key[32..]);
+
+        Ok(cipher)
+    }
+
+    fn header_cipher(&self, sequence_number: u32) -> ChaCha20Legacy {
+        let nonce = (sequence_number as u64).to_be_bytes();
+        ChaCha20Legacy::new(&self.header_key.into(), &nonce.into())
+    }
+
+    /// The cipher for the packet body along with the Poly1305 instance for the tag, the Poly1305
+    /// key is taken from the first block of keystream and the body starts at the second block
+    fn main_cipher(&self, sequence_number: u32) -> (ChaCha20Legacy, Poly1305) {
+        let nonce = (sequence_number as u64).to_be_bytes();
+        let mut cipher = ChaCha20Legacy::new(&self.main_key.into(), &nonce.into());
+
+        let mut poly_key = Zeroizing::new([0; 32]);
+        cipher.apply_keystream(poly_key.as_mut());
+        cipher.seek(64);
+
+        (cipher, Poly1305::new(&(*poly_key).into()))
+    }
+}
+
+impl Cipher for ChaCha20Poly1305 {
+    fn name(&self) -> &str {
+        "chacha20-poly1305@openssh.com"
+    }
+
+    fn get_block_size(&self) -> usize {
+        8
+    }
+
+    fn encrypt(&mut self, _data: &mut [u8]) -> Result<(), CipherError> {
+        Err(CipherError::AeadOnly)
+    }
+
+    fn decrypt(&mut self, _data: &mut [u8]) -> Result<(), CipherError> {
+        Err(CipherError::AeadOnly)
+    }
+
+    fn as_aead(&self) -> Option<&dyn AeadCipher> {
+        Some(self)
+    }
+}
+
+impl AeadCipher for ChaCha20Poly1305 {
+    fn tag_len(&self) -> usize {
+        Self::TAG_LEN
+    }
+
+    fn decrypt_length(&self, sequence_number: u32, mut length: [u8; 4]) -> u32 {
+        self.header_cipher(sequence_number)
+            .apply_keystream(&mut length);
+
+        u32::from_be_bytes(length)
+    }
+
+    fn seal(&self, sequence_number: u32, packet: &mut [u8]) -> Vec<u8> {
+        let (length, body) = packet.split_at_mut(4);
+        self.header_cipher(sequence_number).apply_keystream(length);
+
+        let (mut cipher, poly) = self.main_cipher(sequence_number);
+        cipher.apply_keystream(body);
+
+        poly.compute_unpadded(packet).to_vec()
+    }
+
+    fn open(&self, sequence_number: u32, packet: &mut [u8], tag: &[u8]) -> Result<(), CipherError> {
+        let (mut cipher, poly) = self.main_cipher(sequence_number);
+
+        let expected = poly.compute_unpadded(packet);
+        if !bool::from(expected.as_slice().ct_eq(tag)) {
+            return Err(CipherError::AuthenticationFailed);
+        }
+
+        let (length, body) = packet.split_at_mut(4);
+        self.header_cipher(sequence_number).apply_keystream(length);
+        cipher.apply_keystream(body);
+
+        Ok(())
+    }
+}
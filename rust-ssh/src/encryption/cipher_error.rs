@@ -0,0 +1,39 @@
+use crate::prelude::*;
+
+/// Possible errors when encrypting or decrypting data
+#[derive(Debug, Eq, PartialEq)]
+pub enum CipherError {
+    /// There is no implementation of the cipher available
+    Unimplemented { cipher: String },
+    /// The data was not a multiple of the cipher block size
+    ///
+    /// len: the length of the data
+    /// block_size: the block size of the cipher
+    InvalidDataLength { len: usize, block_size: usize },
+    /// The key or iv given to the cipher had the wrong length
+    InvalidKeyLength { key_len: usize, iv_len: usize },
+    /// The cipher authenticates packets itself and must be used through `AeadCipher`
+    AeadOnly,
+    /// The authentication tag of a packet didn't match its contents
+    AuthenticationFailed,
+}
+
+impl core::fmt::Display for CipherError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Unimplemented { cipher } => write!(f, "The {cipher} cipher is not implemented"),
+            Self::InvalidDataLength { len, block_size } => write!(
+                f,
+                "Data length {len} is not a multiple of the cipher block size {block_size}"
+            ),
+            Self::InvalidKeyLength { key_len, iv_len } => write!(
+                f,
+                "Invalid key length {key_len} or iv length {iv_len} for cipher"
+            ),
+            Self::AeadOnly => write!(f, "The cipher can only be used to encrypt whole packets"),
+            Self::AuthenticationFailed => write!(f, "Packet authentication failed"),
+        }
+    }
+}
+
+impl core::error::Error for CipherError {}
@@ -1,4 +1,22 @@
-#[derive(Debug, PartialEq, Eq)]
+mod aes_ctr;
+#[cfg(feature = "legacy-ciphers")]
+mod arcfour;
+mod chacha20_poly1305;
+mod cipher_error;
+
+pub use aes_ctr::*;
+#[cfg(feature = "legacy-ciphers")]
+pub use arcfour::*;
+pub use chacha20_poly1305::*;
+pub use cipher_error::*;
+
+use crate::prelude::*;
+
+/// The ciphers that can be negotiated in a `KexInitMessage`
+///
+/// Names this crate doesn't know about are kept as `Unknown`, so that a peer's offer can be
+/// decoded and passed on unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CipherType {
     ThreeDESCBC,
     BlowfishCBC,
@@ -12,23 +30,486 @@ pub enum CipherType {
 
     AES192CBC,
     AES128CBC,
+
+    AES256CTR,
+    AES192CTR,
+    AES128CTR,
+
+    AES256GCM,
+    AES128GCM,
+
+    ChaCha20Poly1305,
+
     Serpent256CBC,
 
     Serpent192CBC,
     Serpent128CBC,
 
     ArcFour,
+    ArcFour128,
+    ArcFour256,
     IDEACBC,
     Cast128CBC,
     None,
+    /// A cipher this crate doesn't know about
+    Unknown(String),
+}
+
+impl CipherType {
+    /// Create a working cipher of this type with the given key and iv
+    ///
+    /// Returns an error if the cipher has no implementation yet or the key or iv are the wrong
+    /// length for it.
+    pub fn instantiate(&self, key: &[u8], iv: &[u8]) -> Result<Box<dyn Cipher>, CipherError> {
+        let key_len = match self {
+            Self::AES128CTR => 16,
+            Self::AES192CTR => 24,
+            Self::AES256CTR => 32,
+            Self::ChaCha20Poly1305 => return Ok(Box::new(ChaCha20Poly1305::new(key)?)),
+            Self::None => return Ok(Box::new(Self::None)),
+            #[cfg(feature = "legacy-ciphers")]
+            Self::ArcFour => return Ok(Box::new(ArcFour::new(key)?)),
+            #[cfg(feature = "legacy-ciphers")]
+            Self::ArcFour128 | Self::ArcFour256 => {
+                let key_len = if *self == Self::ArcFour256 { 32 } else { 16 };
+                if key.len() != key_len {
+                    return Err(CipherError::InvalidKeyLength {
+                        key_len: key.len(),
+                        iv_len: iv.len(),
+                    });
+                }
+
+                return Ok(Box::new(ArcFour::new_discarding(key)?));
+            }
+            cipher => {
+                return Err(CipherError::Unimplemented {
+                    cipher: format!("{cipher:?}"),
+                })
+            }
+        };
+
+        if key.len() != key_len {
+            return Err(CipherError::InvalidKeyLength {
+                key_len: key.len(),
+                iv_len: iv.len(),
+            });
+        }
+
+        Ok(Box::new(AesCtr::new(key, iv)?))
+    }
+}
+
+/// The ciphers that have a working implementation, which `CipherType::instantiate` accepts
+///
+/// The RC4 based ciphers are only included with the `legacy-ciphers` feature.
+pub fn supported_ciphers() -> Vec<CipherType> {
+    vec![
+        CipherType::ChaCha20Poly1305,
+        CipherType::AES256CTR,
+        CipherType::AES192CTR,
+        CipherType::AES128CTR,
+        #[cfg(feature = "legacy-ciphers")]
+        CipherType::ArcFour256,
+        #[cfg(feature = "legacy-ciphers")]
+        CipherType::ArcFour128,
+        #[cfg(feature = "legacy-ciphers")]
+        CipherType::ArcFour,
+        CipherType::None,
+    ]
+}
+
+impl core::fmt::Display for CipherType {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl core::str::FromStr for CipherType {
+    type Err = core::convert::Infallible;
+
+    /// Parse the name of a cipher as used in algorithm negotiation, unrecognized names give
+    /// `CipherType::Unknown`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "3des-cbc" => Self::ThreeDESCBC,
+            "blowfish-cbc" => Self::BlowfishCBC,
+            "twofish256-cbc" => Self::Twofish256CBC,
+            "twofish-cbc" => Self::TwofishCBC,
+            "twofish192-cbc" => Self::Twofish192CBC,
+            "twofish128-cbc" => Self::Twofish128CBC,
+            "aes256-cbc" => Self::AES256CBC,
+            "aes192-cbc" => Self::AES192CBC,
+            "aes128-cbc" => Self::AES128CBC,
+            "aes256-ctr" => Self::AES256CTR,
+            "aes192-ctr" => Self::AES192CTR,
+            "aes128-ctr" => Self::AES128CTR,
+            "aes256-gcm@openssh.com" => Self::AES256GCM,
+            "aes128-gcm@openssh.com" => Self::AES128GCM,
+            "chacha20-poly1305@openssh.com" => Self::ChaCha20Poly1305,
+            "serpent256-cbc" => Self::Serpent256CBC,
+            "serpent192-cbc" => Self::Serpent192CBC,
+            "serpent128-cbc" => Self::Serpent128CBC,
+            "arcfour" => Self::ArcFour,
+            "arcfour128" => Self::ArcFour128,
+            "arcfour256" => Self::ArcFour256,
+            "idea-cbc" => Self::IDEACBC,
+            "cast128-cbc" => Self::Cast128CBC,
+            "none" => Self::None,
+            name => Self::Unknown(name.to_string()),
+        })
+    }
 }
 
-pub trait Cipher {
+/// A cipher used to encrypt packets
+///
+/// The `Debug` output names the algorithm but must never include key material.
+pub trait Cipher: core::fmt::Debug {
+    /// The name of the cipher as used in algorithm negotiation, e.g. `aes128-ctr`
+    fn name(&self) -> &str;
+
     fn get_block_size(&self) -> usize;
+
+    /// Encrypt `data` in place, `data` must be a multiple of the block size
+    fn encrypt(&mut self, data: &mut [u8]) -> Result<(), CipherError>;
+
+    /// Decrypt `data` in place, `data` must be a multiple of the block size
+    fn decrypt(&mut self, data: &mut [u8]) -> Result<(), CipherError>;
+
+    /// Get the authenticated encryption interface if this is an AEAD cipher, packets encrypted
+    /// with these don't use a separate `Mac`
+    fn as_aead(&self) -> Option<&dyn AeadCipher> {
+        None
+    }
+}
+
+/// Authenticated encryption ciphers, which encrypt whole packets (including the length field)
+/// using the packet sequence number and produce their own authentication tag
+pub trait AeadCipher {
+    /// The length of the tag appended to each packet
+    fn tag_len(&self) -> usize;
+
+    /// Decrypt only the packet length field, so that we know how much data to wait for
+    fn decrypt_length(&self, sequence_number: u32, length: [u8; 4]) -> u32;
+
+    /// Encrypt a whole packet in place and return the authentication tag for it
+    fn seal(&self, sequence_number: u32, packet: &mut [u8]) -> Vec<u8>;
+
+    /// Verify the authentication tag of a packet and decrypt it in place
+    fn open(&self, sequence_number: u32, packet: &mut [u8], tag: &[u8]) -> Result<(), CipherError>;
 }
 
 impl Cipher for CipherType {
+    fn name(&self) -> &str {
+        match self {
+            Self::ThreeDESCBC => "3des-cbc",
+            Self::BlowfishCBC => "blowfish-cbc",
+            Self::Twofish256CBC => "twofish256-cbc",
+            Self::TwofishCBC => "twofish-cbc",
+            Self::Twofish192CBC => "twofish192-cbc",
+            Self::Twofish128CBC => "twofish128-cbc",
+            Self::AES256CBC => "aes256-cbc",
+            Self::AES192CBC => "aes192-cbc",
+            Self::AES128CBC => "aes128-cbc",
+            Self::AES256CTR => "aes256-ctr",
+            Self::AES192CTR => "aes192-ctr",
+            Self::AES128CTR => "aes128-ctr",
+            Self::AES256GCM => "aes256-gcm@openssh.com",
+            Self::AES128GCM => "aes128-gcm@openssh.com",
+            Self::ChaCha20Poly1305 => "chacha20-poly1305@openssh.com",
+            Self::Serpent256CBC => "serpent256-cbc",
+            Self::Serpent192CBC => "serpent192-cbc",
+            Self::Serpent128CBC => "serpent128-cbc",
+            Self::ArcFour => "arcfour",
+            Self::ArcFour128 => "arcfour128",
+            Self::ArcFour256 => "arcfour256",
+            Self::IDEACBC => "idea-cbc",
+            Self::Cast128CBC => "cast128-cbc",
+            Self::None => "none",
+            Self::Unknown(name) => name,
+        }
+    }
+
     fn get_block_size(&self) -> usize {
-        8
+        match self {
+            Self::ThreeDESCBC
+            | Self::BlowfishCBC
+            | Self::IDEACBC
+            | Self::Cast128CBC
+            | Self::ChaCha20Poly1305 => 8,
+
+            Self::Twofish256CBC
+            | Self::TwofishCBC
+            | Self::Twofish192CBC
+            | Self::Twofish128CBC
+            | Self::AES256CBC
+            | Self::AES192CBC
+            | Self::AES128CBC
+            | Self::AES256CTR
+            | Self::AES192CTR
+            | Self::AES128CTR
+            | Self::AES256GCM
+            | Self::AES128GCM
+            | Self::Serpent256CBC
+            | Self::Serpent192CBC
+            | Self::Serpent128CBC => 16,
+
+            // Stream ciphers (and no cipher at all) don't have a block size
+            Self::ArcFour | Self::ArcFour128 | Self::ArcFour256 | Self::None => 1,
+
+            // We can't know the block size of a cipher we don't know, but it can't be used anyway
+            Self::Unknown(_) => 1,
+        }
+    }
+
+    fn encrypt(&mut self, _data: &mut [u8]) -> Result<(), CipherError> {
+        match self {
+            Self::None => Ok(()),
+            cipher => Err(CipherError::Unimplemented {
+                cipher: format!("{cipher:?}"),
+            }),
+        }
+    }
+
+    fn decrypt(&mut self, _data: &mut [u8]) -> Result<(), CipherError> {
+        match self {
+            Self::None => Ok(()),
+            cipher => Err(CipherError::Unimplemented {
+                cipher: format!("{cipher:?}"),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encryption::*;
+
+    #[test]
+    fn supported_ciphers_instantiate() {
+        for cipher in supported_ciphers() {
+            let (key_len, iv_len) = match cipher {
+                CipherType::ChaCha20Poly1305 => (64, 0),
+                CipherType::AES256CTR | CipherType::ArcFour256 => (32, 16),
+                CipherType::AES192CTR => (24, 16),
+                _ => (16, 16),
+            };
+
+            let instance = cipher.instantiate(&vec![1; key_len], &vec![2; iv_len]);
+            assert_eq!(instance.unwrap().name(), cipher.name());
+        }
+    }
+
+    #[test]
+    fn block_sizes() {
+        let block_sizes = [
+            (CipherType::ThreeDESCBC, 8),
+            (CipherType::BlowfishCBC, 8),
+            (CipherType::Twofish256CBC, 16),
+            (CipherType::TwofishCBC, 16),
+            (CipherType::Twofish192CBC, 16),
+            (CipherType::Twofish128CBC, 16),
+            (CipherType::AES256CBC, 16),
+            (CipherType::AES192CBC, 16),
+            (CipherType::AES128CBC, 16),
+            (CipherType::AES256CTR, 16),
+            (CipherType::AES192CTR, 16),
+            (CipherType::AES128CTR, 16),
+            (CipherType::AES256GCM, 16),
+            (CipherType::AES128GCM, 16),
+            (CipherType::ChaCha20Poly1305, 8),
+            (CipherType::Serpent256CBC, 16),
+            (CipherType::Serpent192CBC, 16),
+            (CipherType::Serpent128CBC, 16),
+            (CipherType::ArcFour, 1),
+            (CipherType::ArcFour128, 1),
+            (CipherType::ArcFour256, 1),
+            (CipherType::IDEACBC, 8),
+            (CipherType::Cast128CBC, 8),
+            (CipherType::None, 1),
+        ];
+
+        for (cipher, block_size) in block_sizes {
+            assert_eq!(cipher.get_block_size(), block_size, "{cipher:?}");
+        }
+    }
+
+    #[test]
+    fn names() {
+        let names = [
+            (CipherType::ThreeDESCBC, "3des-cbc"),
+            (CipherType::BlowfishCBC, "blowfish-cbc"),
+            (CipherType::Twofish256CBC, "twofish256-cbc"),
+            (CipherType::TwofishCBC, "twofish-cbc"),
+            (CipherType::Twofish192CBC, "twofish192-cbc"),
+            (CipherType::Twofish128CBC, "twofish128-cbc"),
+            (CipherType::AES256CBC, "aes256-cbc"),
+            (CipherType::AES192CBC, "aes192-cbc"),
+            (CipherType::AES128CBC, "aes128-cbc"),
+            (CipherType::AES256CTR, "aes256-ctr"),
+            (CipherType::AES192CTR, "aes192-ctr"),
+            (CipherType::AES128CTR, "aes128-ctr"),
+            (CipherType::AES256GCM, "aes256-gcm@openssh.com"),
+            (CipherType::AES128GCM, "aes128-gcm@openssh.com"),
+            (
+                CipherType::ChaCha20Poly1305,
+                "chacha20-poly1305@openssh.com",
+            ),
+            (CipherType::Serpent256CBC, "serpent256-cbc"),
+            (CipherType::Serpent192CBC, "serpent192-cbc"),
+            (CipherType::Serpent128CBC, "serpent128-cbc"),
+            (CipherType::ArcFour, "arcfour"),
+            (CipherType::ArcFour128, "arcfour128"),
+            (CipherType::ArcFour256, "arcfour256"),
+            (CipherType::IDEACBC, "idea-cbc"),
+            (CipherType::Cast128CBC, "cast128-cbc"),
+            (CipherType::None, "none"),
+        ];
+
+        for (cipher, name) in names {
+            assert_eq!(cipher.name(), name, "{cipher:?}");
+            assert_eq!(cipher.to_string(), name, "{cipher:?}");
+            assert_eq!(name.parse::<CipherType>(), Ok(cipher));
+        }
+
+        let unknown = CipherType::Unknown("rijndael-cbc@lysator.liu.se".to_string());
+        assert_eq!(unknown.name(), "rijndael-cbc@lysator.liu.se");
+        assert_eq!("rijndael-cbc@lysator.liu.se".parse(), Ok(unknown));
+
+        // The working ciphers report the same name as the type they were created from
+        for (cipher_type, key_len) in [
+            (CipherType::AES128CTR, 16),
+            (CipherType::AES192CTR, 24),
+            (CipherType::AES256CTR, 32),
+            (CipherType::ChaCha20Poly1305, 64),
+        ] {
+            let cipher = cipher_type
+                .instantiate(&vec![0; key_len], &[0; 16])
+                .unwrap();
+            assert_eq!(cipher.name(), cipher_type.name());
+        }
+    }
+
+    #[cfg(feature = "legacy-ciphers")]
+    #[test]
+    fn arcfour_instantiates() {
+        for (cipher_type, key_len) in [
+            (CipherType::ArcFour, 16),
+            (CipherType::ArcFour128, 16),
+            (CipherType::ArcFour256, 32),
+        ] {
+            let cipher = cipher_type.instantiate(&vec![0; key_len], &[]).unwrap();
+            assert_eq!(cipher.name(), cipher_type.name());
+        }
+
+        // The key length has to match the variant, not just be valid for RC4
+        assert!(CipherType::ArcFour128.instantiate(&[0; 32], &[]).is_err());
+    }
+
+    #[test]
+    fn none_cipher_is_a_no_op() {
+        let mut data = *b"some data";
+
+        CipherType::None.encrypt(&mut data).unwrap();
+        assert_eq!(&data, b"some data");
+
+        CipherType::None.decrypt(&mut data).unwrap();
+        assert_eq!(&data, b"some data");
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn aes_ctr_known_answer() {
+        // NIST SP 800-38A F.5.1 and F.5.5
+        let iv = from_hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let plaintext =
+            from_hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+
+        let vectors = [
+            (
+                CipherType::AES128CTR,
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff",
+            ),
+            (
+                CipherType::AES256CTR,
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5",
+            ),
+        ];
+
+        for (cipher_type, key, ciphertext) in vectors {
+            let key = from_hex(key);
+
+            let mut data = plaintext.clone();
+            let mut cipher = cipher_type.instantiate(&key, &iv).unwrap();
+            // Encrypt one block at a time to make sure the counter carries over between calls
+            cipher.encrypt(&mut data[..16]).unwrap();
+            cipher.encrypt(&mut data[16..]).unwrap();
+            assert_eq!(data, from_hex(ciphertext));
+
+            let mut cipher = cipher_type.instantiate(&key, &iv).unwrap();
+            cipher.decrypt(&mut data).unwrap();
+            assert_eq!(data, plaintext);
+        }
+    }
+
+    #[test]
+    fn keyed_ciphers_zeroize_on_drop() {
+        fn zeroize_on_drop<T: zeroize::ZeroizeOnDrop>(_: &T) {}
+
+        let chacha = ChaCha20Poly1305::new(&[1; 64]).unwrap();
+        zeroize_on_drop(&chacha);
+        drop(chacha);
+
+        let aes = AesCtr::new(&[1; 32], &[2; 16]).unwrap();
+        zeroize_on_drop(&aes);
+        drop(aes);
+
+        #[cfg(feature = "legacy-ciphers")]
+        {
+            let arcfour = ArcFour::new_discarding(&[1; 32]).unwrap();
+            zeroize_on_drop(&arcfour);
+            drop(arcfour);
+        }
+
+        for (cipher_type, key_len) in [
+            (CipherType::AES128CTR, 16),
+            (CipherType::AES192CTR, 24),
+            (CipherType::ChaCha20Poly1305, 64),
+        ] {
+            let cipher = cipher_type
+                .instantiate(&vec![1; key_len], &[2; 16])
+                .unwrap();
+            drop(cipher);
+        }
+    }
+
+    #[test]
+    fn debug_hides_keys() {
+        let cipher = CipherType::AES128CTR
+            .instantiate(&[0xab; 16], &[0xcd; 16])
+            .unwrap();
+        assert_eq!(format!("{cipher:?}"), "AesCtr(Aes128)");
+
+        let cipher = ChaCha20Poly1305::new(&[0xab; 64]).unwrap();
+        assert_eq!(format!("{cipher:?}"), "ChaCha20Poly1305 { .. }");
+    }
+
+    #[test]
+    fn instantiate_errors() {
+        assert!(matches!(
+            CipherType::AES128CTR.instantiate(&[0; 32], &[0; 16]),
+            Err(CipherError::InvalidKeyLength { .. })
+        ));
+        assert!(matches!(
+            CipherType::BlowfishCBC.instantiate(&[0; 16], &[0; 8]),
+            Err(CipherError::Unimplemented { .. })
+        ));
     }
 }
@@ -0,0 +1,135 @@
+//! Server host keys and the signatures made with them
+
+mod sig_error;
+
+pub use sig_error::*;
+
+use crate::codec::Reader;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+
+/// The algorithm name used in `ssh-ed25519` key and signature blobs
+const ED25519: &str = "ssh-ed25519";
+
+/// Read the algorithm name at the start of a key or signature blob and check it is `expected`
+fn take_algorithm(reader: &mut Reader, expected: &str) -> Result<(), SigError> {
+    let actual = reader.get_utf8()?;
+
+    match actual == expected {
+        true => Ok(()),
+        false => Err(SigError::UnexpectedAlgorithm {
+            expected: expected.into(),
+            actual,
+        }),
+    }
+}
+
+/// An `ssh-ed25519` host key as defined by RFC 8709
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ed25519HostKey {
+    key: VerifyingKey,
+}
+
+impl Ed25519HostKey {
+    /// Parse a public key blob, `string "ssh-ed25519" || string key`
+    pub fn from_blob(blob: &[u8]) -> Result<Self, SigError> {
+        let mut reader = Reader::new(blob);
+
+        take_algorithm(&mut reader, ED25519)?;
+        let key = reader.get_string()?;
+        reader.expect_end()?;
+
+        let invalid_key = || SigError::InvalidKey { len: key.len() };
+        let key: [u8; 32] = key.try_into().map_err(|_| invalid_key())?;
+        let key = VerifyingKey::from_bytes(&key).map_err(|_| invalid_key())?;
+
+        Ok(Self { key })
+    }
+
+    /// The raw 32 byte public key
+    pub fn public_key(&self) -> [u8; 32] {
+        self.key.to_bytes()
+    }
+
+    /// Verify a signature blob, `string "ssh-ed25519" || string signature`, over the exchange
+    /// hash
+    pub fn verify(&self, exchange_hash: &[u8], signature_blob: &[u8]) -> Result<(), SigError> {
+        let mut reader = Reader::new(signature_blob);
+
+        take_algorithm(&mut reader, ED25519)?;
+        let signature = reader.get_string()?;
+        reader.expect_end()?;
+
+        let signature: [u8; 64] =
+            signature
+                .try_into()
+                .map_err(|_| SigError::InvalidSignatureLength {
+                    len: signature.len(),
+                })?;
+
+        self.key
+            .verify_strict(exchange_hash, &Signature::from_bytes(&signature))
+            .map_err(|_| SigError::InvalidSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::Writer;
+    use crate::hostkey::*;
+    use crate::prelude::*;
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn blob(algorithm: &str, data: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.put_string(algorithm.as_bytes());
+        writer.put_string(data);
+        writer.into_bytes()
+    }
+
+    // RFC 8032 section 7.1 test 1, which signs an empty message
+    const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+    #[test]
+    fn verify_rfc_8032_signature() {
+        let key = Ed25519HostKey::from_blob(&blob("ssh-ed25519", &hex(PUBLIC_KEY))).unwrap();
+
+        assert_eq!(key.public_key().to_vec(), hex(PUBLIC_KEY));
+        assert_eq!(
+            key.verify(b"", &blob("ssh-ed25519", &hex(SIGNATURE))),
+            Ok(())
+        );
+        assert_eq!(
+            key.verify(b"x", &blob("ssh-ed25519", &hex(SIGNATURE))),
+            Err(SigError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn malformed_blobs() {
+        assert_eq!(
+            Ed25519HostKey::from_blob(&blob("ssh-rsa", &hex(PUBLIC_KEY))),
+            Err(SigError::UnexpectedAlgorithm {
+                expected: "ssh-ed25519".into(),
+                actual: "ssh-rsa".into()
+            })
+        );
+        assert_eq!(
+            Ed25519HostKey::from_blob(&blob("ssh-ed25519", &[0; 31])),
+            Err(SigError::InvalidKey { len: 31 })
+        );
+
+        let key = Ed25519HostKey::from_blob(&blob("ssh-ed25519", &hex(PUBLIC_KEY))).unwrap();
+        assert_eq!(
+            key.verify(b"", &blob("ssh-ed25519", &hex(SIGNATURE)[..63])),
+            Err(SigError::InvalidSignatureLength { len: 63 })
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use crate::message::DecodeError;
+use crate::prelude::*;
+
+/// Possible errors when parsing a host key or verifying a signature
+#[derive(Debug, Eq, PartialEq)]
+pub enum SigError {
+    /// A key or signature blob wasn't in the expected format
+    Decode(DecodeError),
+    /// A key or signature blob was for a different algorithm
+    ///
+    /// expected: the algorithm name of the key
+    /// actual: the algorithm name in the blob
+    UnexpectedAlgorithm { expected: String, actual: String },
+    /// The public key had the wrong length or wasn't a valid point
+    InvalidKey { len: usize },
+    /// The signature had the wrong length
+    InvalidSignatureLength { len: usize },
+    /// The signature didn't match the data
+    InvalidSignature,
+}
+
+impl core::fmt::Display for SigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Decode(e) => write!(f, "Invalid key or signature blob: {e}"),
+            Self::UnexpectedAlgorithm { expected, actual } => {
+                write!(f, "Expected a {expected} blob, got {actual}")
+            }
+            Self::InvalidKey { len } => write!(f, "Invalid public key of length {len}"),
+            Self::InvalidSignatureLength { len } => write!(f, "Invalid signature length {len}"),
+            Self::InvalidSignature => write!(f, "Signature verification failed"),
+        }
+    }
+}
+
+impl core::error::Error for SigError {}
+
+impl From<DecodeError> for SigError {
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
@@ -1,4 +1,7 @@
+use crate::prelude::*;
+
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Possible errors in creating a SSH Protocol Identification string
 pub enum IdentificationError {
     /// The maximum length of the identification string was exceeded
@@ -13,44 +16,113 @@ pub enum IdentificationError {
         index: usize,
         value: String,
     },
+    /// A character outside of the printable US-ASCII range (0x20-0x7E) was found
+    ///
+    /// index: The byte index of the character in the identification string
+    /// value: the String value itself
+    NonAsciiCharacter {
+        index: usize,
+        value: String,
+    },
 
+    /// The identification string didn't end with the line ending its protocol version requires
+    ///
+    /// expected: the line ending that was required, `"\r\n"` or `"\n"`
+    /// actual: the last few characters of the string, which were found instead
+    /// offset: the byte offset in the input where the ending was expected
     InvalidEnding {
+        expected: String,
         actual: String,
+        offset: usize,
     },
     InvalidStringBeginning {
         actual: String,
     },
+    /// The protocol version wasn't one we can parse
+    ///
+    /// actual: the protocol version that was found
+    /// offset: the byte offset in the input where the protocol version starts
     InvalidProtocolVersion {
         actual: String,
+        offset: usize,
     },
 
     UnsupportedProtocolVersion {
         ver: String,
     },
 
+    /// The software version wasn't followed by a space before the comments
+    ///
+    /// offset: the byte offset in the input of the unexpected character
     ExpectedSpaceSeparator {
         actual: char,
         value: String,
+        offset: usize,
+    },
+
+    /// The protocol version wasn't followed by a '-' and a software version
+    ///
+    /// offset: the byte offset in the input where the '-' was expected
+    MissingSoftwareVersion {
+        offset: usize,
+    },
+
+    /// The software version contains a character RFC 4253 doesn't allow in it
+    ///
+    /// reason: which character was found
+    InvalidSoftwareVersion {
+        reason: String,
     },
 
-    MissingSoftwareVersion,
+    /// The stream ended before an identification string was found
+    UnexpectedEndOfStream,
+    /// The peer sent more lines before the identification string than we are willing to read
+    TooManyPreambleLines {
+        limit: usize,
+    },
+    /// Reading from the underlying stream failed
+    ///
+    /// `std::io::ErrorKind` can't be serialized, so this variant is skipped by serde
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ReadError {
+        kind: std::io::ErrorKind,
+    },
+    /// Writing to the underlying stream failed
+    ///
+    /// `std::io::ErrorKind` can't be serialized, so this variant is skipped by serde
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    WriteError {
+        kind: std::io::ErrorKind,
+    },
 }
 
-impl std::fmt::Display for IdentificationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for IdentificationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
             Self::MaxLengthExceeded {length, value} => write!(f, "Identification String must not exceed 256 characters (got {length}); Actual result string was {value}"),
             Self::ContainsNullCharacter {index, value} => write!(f, "Identification String must not contain the null character '\0', found at index {index} in \"{value}\""),
+            Self::NonAsciiCharacter {index, value} => write!(f, "Identification String must only contain printable US-ASCII characters, found invalid character at index {index} in \"{value}\""),
 
-            Self::InvalidEnding { actual } => write!(f, "Expected string to end with a carriage return followed by a line feed \"\\r\\n\", found \"{actual}\""),
+            Self::InvalidEnding { expected, actual, offset } => write!(f, "Expected string to end with {expected:?} at offset {offset}, found {actual:?}"),
             Self::InvalidStringBeginning { actual } => write!(f, "Expected identification string to start with \"SSH-\", got {actual}"),
-            Self::InvalidProtocolVersion { actual } => write!(f, "Expected a supported protocol version (2.0 or 1.99) got {actual}"),
+            Self::InvalidProtocolVersion { actual, offset } => write!(f, "Expected a supported protocol version (2.0 or 1.99) at offset {offset}, got {actual}"),
 
             Self::UnsupportedProtocolVersion { ver } => write!(f, "Got an unsupported version of the ssh protocol: expected (2.0 or 1.99) got {ver}"),
-            Self::MissingSoftwareVersion => write!(f, "No software version identifier in identification string"),
-            Self::ExpectedSpaceSeparator { actual, value } => write!(f, "Expected a space character separating protocol version and comments, got {actual} in (\"{value}\")"),
+            Self::MissingSoftwareVersion { offset } => write!(f, "No software version identifier in identification string, expected '-' at offset {offset}"),
+            Self::ExpectedSpaceSeparator { actual, value, offset } => write!(f, "Expected a space character separating protocol version and comments, got {actual} at offset {offset} in (\"{value}\")"),
+
+            Self::InvalidSoftwareVersion { reason } => write!(f, "Invalid software version: {reason}"),
+
+            Self::UnexpectedEndOfStream => write!(f, "Stream ended before an identification string was received"),
+            Self::TooManyPreambleLines { limit } => write!(f, "No identification string was received within the first {limit} lines"),
+            #[cfg(feature = "std")]
+            Self::ReadError { kind } => write!(f, "Failed to read identification string: {kind}"),
+            #[cfg(feature = "std")]
+            Self::WriteError { kind } => write!(f, "Failed to write identification string: {kind}"),
         }
     }
 }
 
-impl std::error::Error for IdentificationError {}
+impl core::error::Error for IdentificationError {}
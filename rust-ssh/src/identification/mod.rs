@@ -1,14 +1,29 @@
 mod identification_error;
+pub mod quirk;
 pub mod ssh_version;
 
-use identification_error::*;
+pub use identification_error::*;
+use quirk::*;
 use ssh_version::*;
 
+use crate::prelude::*;
+
+/// The maximum number of lines a peer may send before its identification string.
+///
+/// RFC 4253 allows a server to send other lines of data first, but doesn't put a limit on how
+/// many, so we cap it to avoid reading forever from a hostile peer.
+pub const MAX_PREAMBLE_LINES: usize = 1024;
+
+/// The maximum length of an identification string (or preamble line) including the line ending
+#[cfg(feature = "std")]
+const MAX_LINE_LENGTH: usize = 255;
+
 /// The identification information for a ssh client or server as defined by IETF RFC 4253.
 ///
 /// The most important function on this data structure is `try_encode_to_string` which will
 /// attempt to create a identification string to be sent to the other partner in a connection.
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Identification {
     protocol_version: SSHVersion,
     software_version: String,
@@ -39,17 +54,49 @@ impl Identification {
         }
     }
 
+    /// The ssh protocol version
+    pub fn protocol_version(&self) -> &SSHVersion {
+        &self.protocol_version
+    }
+
+    /// The name and version of the software implementing the protocol
+    pub fn software_version(&self) -> &str {
+        &self.software_version
+    }
+
+    /// Any comments sent after the software version
+    pub fn comments(&self) -> Option<&str> {
+        self.comments.as_deref()
+    }
+
+    /// The bug compatibility workarounds that need to be enabled when talking to this software
+    ///
+    /// The quirks are found by matching the software version against the `KNOWN_QUIRKS` table.
+    pub fn known_quirks(&self) -> Vec<Quirk> {
+        let mut quirks = vec![];
+
+        for (pattern, quirk) in KNOWN_QUIRKS {
+            if self.software_version.contains(pattern) && !quirks.contains(quirk) {
+                quirks.push(*quirk);
+            }
+        }
+
+        quirks
+    }
+
     /// Attempt to create the Identification string that will be sent to the server/client;
     ///
     /// The function will return an error if the string including comments and the carriage return
     /// line feed combination would be more than `255` characters as this is the maximum length
-    /// allowed by the spec
+    /// allowed by the spec, or if the software version or comments contain anything other than
+    /// printable US-ASCII characters. The software version also may not contain spaces or '-'
+    /// characters, since those separate it from the comments and the protocol version.
     pub fn try_encode_to_string(&self) -> Result<String, IdentificationError> {
-        use crate::utils::character_constants::{CR, LF, SP};
+        use crate::utils::character_constants::{CR, LF};
 
         let ending = match self.protocol_version {
             SSHVersion::Ver2 => format!("{CR}{LF}"),
-            SSHVersion::Ver1 { minor: 99 } => format!("{LF}"),
+            SSHVersion::Ver1 { minor: 99 } => LF.to_string(),
             SSHVersion::Ver1 { minor } => {
                 return Err(IdentificationError::UnsupportedProtocolVersion {
                     ver: format!("1.{minor}"),
@@ -57,18 +104,9 @@ impl Identification {
             }
         };
 
-        let identification_string = format!(
-            "SSH-{}-{}{}{ending}",
-            // Convert the protocol enum to a string value
-            // TODO: Add a display implementation so we don't have to call this dumb function
-            self.protocol_version,
-            self.software_version,
-            // We only need a space before the '\r\n' if we have comments so insert it here
-            match &self.comments {
-                Some(comments) => format!("{SP}{comments}"),
-                None => format!(""),
-            }
-        );
+        validate_software_version(&self.software_version)?;
+
+        let identification_string = format!("{self}{ending}");
 
         // length for validation
         let id_string_len = identification_string.len();
@@ -84,17 +122,222 @@ impl Identification {
                 index,
                 value: identification_string,
             })
+        } else if let Some(index) =
+            find_non_printable(&identification_string[..id_string_len - ending.len()])
+        {
+            Err(IdentificationError::NonAsciiCharacter {
+                index,
+                value: identification_string,
+            })
         } else {
             Ok(identification_string)
         }
     }
 
+    /// Attempt to decode the protocol information from a string
+    ///
+    /// This is equivalent to parsing the string with `str::parse`, see the `FromStr`
+    /// implementation for details.
+    pub fn decode_from_string(identification_string: String) -> Result<Self, IdentificationError> {
+        identification_string.parse()
+    }
+
+    /// Attempt to decode the protocol information from a string, accepting only SSH 2.0
+    ///
+    /// This is `decode_from_string` without the compatibility mode: the `1.99` protocol version
+    /// gives `IdentificationError::UnsupportedProtocolVersion` and the string must end with a
+    /// carriage return+line feed combo.
+    pub fn decode_from_string_strict(
+        identification_string: String,
+    ) -> Result<Self, IdentificationError> {
+        let identification = Self::decode_from_string(identification_string)?;
+
+        // Only 2.0 identification strings are required to end with CRLF, so rejecting every
+        // other version also rules out the bare LF ending
+        match identification.protocol_version {
+            SSHVersion::Ver2 => Ok(identification),
+            version => Err(IdentificationError::UnsupportedProtocolVersion {
+                ver: version.to_string(),
+            }),
+        }
+    }
+
+    /// Read the identification string of the other side of a connection from a stream
+    ///
+    /// Any lines received before the identification string are returned (without their line
+    /// endings) along with the decoded identification, as RFC 4253 allows servers to send other
+    /// data first. At most `MAX_PREAMBLE_LINES` lines are read before giving up, and no line may
+    /// be longer than 255 characters.
+    #[cfg(feature = "std")]
+    pub fn read_from<R: std::io::BufRead>(
+        reader: &mut R,
+    ) -> Result<(Vec<String>, Self), IdentificationError> {
+        use std::io::{BufRead, Read};
+
+        let mut preamble = vec![];
+
+        while preamble.len() < MAX_PREAMBLE_LINES {
+            let mut line = vec![];
+
+            // Read one byte more than the maximum length so that we can tell an over long line
+            // apart from one that is exactly the maximum length
+            reader
+                .by_ref()
+                .take(MAX_LINE_LENGTH as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .map_err(|e| IdentificationError::ReadError { kind: e.kind() })?;
+
+            match Self::parse_line(line)? {
+                Line::Identification(identification) => return Ok((preamble, identification)),
+                Line::Preamble(line) => preamble.push(line),
+            }
+        }
+
+        Err(IdentificationError::TooManyPreambleLines {
+            limit: MAX_PREAMBLE_LINES,
+        })
+    }
+
+    /// Read the identification string of the other side of a connection from an async stream
+    ///
+    /// This is the same as `read_from`, but doesn't block while waiting for the peer.
+    #[cfg(feature = "tokio")]
+    pub async fn read_from_async<R: tokio::io::AsyncBufRead + Unpin>(
+        reader: &mut R,
+    ) -> Result<(Vec<String>, Self), IdentificationError> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+        let mut preamble = vec![];
+
+        while preamble.len() < MAX_PREAMBLE_LINES {
+            let mut line = vec![];
+
+            (&mut *reader)
+                .take(MAX_LINE_LENGTH as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .await
+                .map_err(|e| IdentificationError::ReadError { kind: e.kind() })?;
+
+            match Self::parse_line(line)? {
+                Line::Identification(identification) => return Ok((preamble, identification)),
+                Line::Preamble(line) => preamble.push(line),
+            }
+        }
+
+        Err(IdentificationError::TooManyPreambleLines {
+            limit: MAX_PREAMBLE_LINES,
+        })
+    }
+
+    /// Send the identification string to the other side of a connection over an async stream
+    #[cfg(feature = "tokio")]
+    pub async fn write_to_async<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), IdentificationError> {
+        use tokio::io::AsyncWriteExt;
+
+        let identification_string = self.try_encode_to_string()?;
+
+        writer
+            .write_all(identification_string.as_bytes())
+            .await
+            .map_err(|e| IdentificationError::WriteError { kind: e.kind() })?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| IdentificationError::WriteError { kind: e.kind() })
+    }
+
+    /// Check one line read by `read_from` or `read_from_async`, which is either the identification
+    /// string or a line of data sent before it
+    #[cfg(feature = "std")]
+    fn parse_line(line: Vec<u8>) -> Result<Line, IdentificationError> {
+        let line = match String::from_utf8(line) {
+            Ok(line) => line,
+            Err(e) => {
+                return Err(IdentificationError::NonAsciiCharacter {
+                    index: e.utf8_error().valid_up_to(),
+                    value: String::from_utf8_lossy(e.as_bytes()).into(),
+                })
+            }
+        };
+
+        if line.len() > MAX_LINE_LENGTH {
+            return Err(IdentificationError::MaxLengthExceeded {
+                length: line.len(),
+                value: line,
+            });
+        }
+
+        if line.starts_with("SSH-") {
+            return Ok(Line::Identification(Self::decode_from_string(line)?));
+        }
+
+        if !line.ends_with('\n') {
+            return Err(IdentificationError::UnexpectedEndOfStream);
+        }
+
+        Ok(Line::Preamble(line.trim_end_matches(['\r', '\n']).into()))
+    }
+}
+
+/// A line received from the peer while waiting for its identification string
+#[cfg(feature = "std")]
+enum Line {
+    Identification(Identification),
+    /// Other data sent before the identification string, without the line ending
+    Preamble(String),
+}
+
+/// Check that a software version can be sent without changing how the peer parses it
+fn validate_software_version(software_version: &str) -> Result<(), IdentificationError> {
+    let reason = match software_version.find([' ', '-']) {
+        Some(index) => format!(
+            "{:?} is not allowed (found at index {index})",
+            &software_version[index..index + 1]
+        ),
+        None => match software_version
+            .char_indices()
+            .find(|(_, c)| c.is_control())
+        {
+            Some((index, c)) => {
+                format!("control character {c:?} is not allowed (found at index {index})")
+            }
+            None => return Ok(()),
+        },
+    };
+
+    Err(IdentificationError::InvalidSoftwareVersion { reason })
+}
+
+/// The last `count` bytes of `value`, or all of it if it is shorter
+fn last_chars(value: &str, count: usize) -> &str {
+    &value[value.len().saturating_sub(count)..]
+}
+
+/// Find the byte index of the first character that is not printable US-ASCII (0x20-0x7E) as
+/// required by RFC 4253 for the protocol version, software version and comments
+fn find_non_printable(value: &str) -> Option<usize> {
+    value
+        .char_indices()
+        .find(|(_, c)| !matches!(c, '\x20'..='\x7E'))
+        .map(|(index, _)| index)
+}
+
+impl core::str::FromStr for Identification {
+    type Err = IdentificationError;
+
     /// Attempt to decode the protocol information from a string
     ///
     /// The function will attempt to parse the identifier string in a RFC 4253 compatible way and
     /// fall back to compatibility mode if that fails (e.g. allowing the identification string to
     /// end with a single line feed character rather than a carriage return+line feed combo
-    pub fn decode_from_string(identification_string: String) -> Result<Self, IdentificationError> {
+    ///
+    /// The software version runs from the '-' after the protocol version to the first space or
+    /// the line ending. Peers don't always follow the RFC, so hyphens in it are accepted here even
+    /// though `try_encode_to_string` refuses to send them.
+    fn from_str(identification_string: &str) -> Result<Self, Self::Err> {
         use crate::utils::character_constants::{CR, LF};
 
         let id_string_len = identification_string.len();
@@ -104,20 +347,35 @@ impl Identification {
         if id_string_len > 255 {
             return Err(IdentificationError::MaxLengthExceeded {
                 length: id_string_len,
-                value: identification_string,
+                value: identification_string.into(),
             });
         } else if let Some(index) = identification_string.find('\0') {
             return Err(IdentificationError::ContainsNullCharacter {
                 index,
-                value: identification_string,
+                value: identification_string.into(),
+            });
+        }
+
+        // Everything before the line ending must be printable US-ASCII, this also guarantees that
+        // byte indices line up with characters so slicing the string below can't panic
+        let content = identification_string
+            .strip_suffix(LF)
+            .map(|s| s.strip_suffix(CR).unwrap_or(s))
+            .unwrap_or(identification_string);
+        if let Some(index) = find_non_printable(content) {
+            return Err(IdentificationError::NonAsciiCharacter {
+                index,
+                value: identification_string.into(),
             });
         }
 
         // Verify that this is indeed intended to be a SSH Identification string and not another
         // line of data that the ssh protocol allows to be sent before the identifier.
         if !identification_string.starts_with("SSH-") {
+            // The string may be shorter than the expected prefix (e.g. a partial read from a
+            // socket) so only report what is actually available
             return Err(IdentificationError::InvalidStringBeginning {
-                actual: identification_string[0..4].into(),
+                actual: identification_string.chars().take(4).collect(),
             });
         }
 
@@ -133,7 +391,10 @@ impl Identification {
                 (SSHVersion::Ver1 { minor: 99 }, &rest[version.len()..])
             }
             Some(ver) => {
-                return Err(IdentificationError::InvalidProtocolVersion { actual: ver.into() })
+                return Err(IdentificationError::InvalidProtocolVersion {
+                    actual: ver.into(),
+                    offset: 4,
+                })
             }
             None => unreachable!(),
         };
@@ -143,18 +404,25 @@ impl Identification {
             SSHVersion::Ver2 => {
                 // Verify that the identifier string has a correct ending
                 if !identification_string.ends_with(&format!("{CR}{LF}")) {
-                    let ending_start_index = identification_string.len() - 3;
+                    // A bare line feed is where the carriage return should have been
+                    let offset = match identification_string.strip_suffix(LF) {
+                        Some(line) => line.len(),
+                        None => identification_string.len(),
+                    };
                     return Err(IdentificationError::InvalidEnding {
-                        actual: identification_string[ending_start_index..].into(),
+                        expected: format!("{CR}{LF}"),
+                        actual: last_chars(identification_string, 3).into(),
+                        offset,
                     });
                 }
                 2
             }
             SSHVersion::Ver1 { minor: 99 } => {
-                if !identification_string.ends_with(&format!("{LF}")) {
-                    let ending_start_index = identification_string.len() - 2;
+                if !identification_string.ends_with(LF) {
                     return Err(IdentificationError::InvalidEnding {
-                        actual: identification_string[ending_start_index..].into(),
+                        expected: LF.to_string(),
+                        actual: last_chars(identification_string, 2).into(),
+                        offset: identification_string.len(),
                     });
                 }
                 1
@@ -167,8 +435,13 @@ impl Identification {
             }
         };
 
+        // The byte offset of the software version in the input
+        let software_version_offset = identification_string.len() - rest.len() + 1;
+
         if !rest.starts_with('-') {
-            return Err(IdentificationError::MissingSoftwareVersion);
+            return Err(IdentificationError::MissingSoftwareVersion {
+                offset: software_version_offset - 1,
+            });
         }
         let rest = &rest[1..];
 
@@ -179,7 +452,11 @@ impl Identification {
                     // and strip the crlf from the string
                     (version, &rest[version.len() + 1..rest.len() - end_len])
                 }
-                None => return Err(IdentificationError::MissingSoftwareVersion),
+                None => {
+                    return Err(IdentificationError::MissingSoftwareVersion {
+                        offset: software_version_offset,
+                    })
+                }
             }
         } else {
             // No comments string is present so we just strip the crlf from the end of the string
@@ -199,6 +476,24 @@ impl Identification {
     }
 }
 
+impl core::fmt::Display for Identification {
+    /// Format the identification string as it appears on the wire, without the trailing line
+    /// ending (`\r\n` or `\n`, depending on the protocol version).
+    ///
+    /// No validation is performed, use `try_encode_to_string` to get a string that is safe to send.
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        use crate::utils::character_constants::SP;
+
+        write!(f, "SSH-{}-{}", self.protocol_version, self.software_version)?;
+
+        // We only need a space before the line ending if we have comments so insert it here
+        match &self.comments {
+            Some(comments) => write!(f, "{SP}{comments}"),
+            None => Ok(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::identification::*;
@@ -246,4 +541,377 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn decode_software_version_without_comment() {
+        for (input, protocol_version, software_version) in [
+            ("SSH-2.0-Go\r\n", SSHVersion::Ver2, "Go"),
+            (
+                "SSH-2.0-OpenSSH_7.6p1\r\n",
+                SSHVersion::Ver2,
+                "OpenSSH_7.6p1",
+            ),
+            ("SSH-2.0-Foo-1.0\r\n", SSHVersion::Ver2, "Foo-1.0"),
+            (
+                "SSH-1.99-Foo-1.0\n",
+                SSHVersion::Ver1 { minor: 99 },
+                "Foo-1.0",
+            ),
+        ] {
+            assert_eq!(
+                Identification::decode_from_string(input.into()),
+                Ok(Identification::new(
+                    protocol_version,
+                    software_version.into(),
+                    None
+                )),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn comment_starts_at_first_space() {
+        // Splitting on '-' instead of the first space would cut the software version short
+        assert_eq!(
+            Identification::decode_from_string("SSH-2.0-Foo-1.0 some comment\r\n".into()),
+            Ok(Identification::new(
+                SSHVersion::Ver2,
+                "Foo-1.0".into(),
+                Some("some comment".into())
+            ))
+        );
+    }
+
+    #[test]
+    fn comment_keeps_internal_spaces() {
+        let ident = Identification::decode_from_string(
+            "SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.1 extra note\r\n".into(),
+        )
+        .unwrap();
+
+        assert_eq!(ident.software_version(), "OpenSSH_8.9p1");
+        assert_eq!(ident.comments(), Some("Ubuntu-3ubuntu0.1 extra note"));
+    }
+
+    #[test]
+    fn display_omits_line_ending() {
+        let ident = Identification::new(
+            SSHVersion::Ver2,
+            "OpenSSH_7.6p1".into(),
+            Some("Ubuntu-4ubuntu0.5".into()),
+        );
+
+        assert_eq!(ident.to_string(), "SSH-2.0-OpenSSH_7.6p1 Ubuntu-4ubuntu0.5");
+        assert_eq!(
+            Identification::new(SSHVersion::Ver2, "rssh1.0".into(), None).to_string(),
+            "SSH-2.0-rssh1.0"
+        );
+    }
+
+    #[test]
+    fn decode_short_strings() {
+        for (input, actual) in [("", ""), ("S", "S"), ("SSH", "SSH")] {
+            assert_eq!(
+                Identification::decode_from_string(input.into()),
+                Err(IdentificationError::InvalidStringBeginning {
+                    actual: actual.into()
+                })
+            );
+        }
+
+        assert_eq!(
+            Identification::decode_from_string("SSH-".into()),
+            Err(IdentificationError::InvalidProtocolVersion {
+                actual: "".into(),
+                offset: 4
+            })
+        );
+    }
+
+    #[test]
+    fn decode_errors_report_offsets() {
+        assert_eq!(
+            Identification::decode_from_string("SSH-3.0-rssh\r\n".into()),
+            Err(IdentificationError::InvalidProtocolVersion {
+                actual: "3.0".into(),
+                offset: 4
+            })
+        );
+        assert_eq!(
+            Identification::decode_from_string("SSH-2.0-rssh\n".into()),
+            Err(IdentificationError::InvalidEnding {
+                expected: "\r\n".into(),
+                actual: "sh\n".into(),
+                offset: 12
+            })
+        );
+        assert_eq!(
+            Identification::decode_from_string("SSH-1.99-rssh".into()),
+            Err(IdentificationError::InvalidEnding {
+                expected: "\n".into(),
+                actual: "sh".into(),
+                offset: 13
+            })
+        );
+
+        // Strings without any line ending error instead of panicking
+        assert_eq!(
+            Identification::decode_from_string("SSH-2.0-x".into()),
+            Err(IdentificationError::InvalidEnding {
+                expected: "\r\n".into(),
+                actual: "0-x".into(),
+                offset: 9
+            })
+        );
+        assert_eq!(
+            Identification::decode_from_string("SSH-2.0".into()),
+            Err(IdentificationError::InvalidEnding {
+                expected: "\r\n".into(),
+                actual: "2.0".into(),
+                offset: 7
+            })
+        );
+
+        // The message names the ending the protocol version requires
+        let error = Identification::decode_from_string("SSH-1.99-rssh".into()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected string to end with \"\\n\" at offset 13, found \"sh\""
+        );
+
+        let error = IdentificationError::MissingSoftwareVersion { offset: 7 };
+        assert!(error.to_string().contains("offset 7"));
+    }
+
+    #[test]
+    fn encode_rejects_non_ascii_comments() {
+        for comment in ["caf\u{e9}", "rocket \u{1F680}"] {
+            let ident = Identification::new(SSHVersion::Ver2, "rssh".into(), Some(comment.into()));
+
+            assert_eq!(
+                ident.try_encode_to_string(),
+                Err(IdentificationError::NonAsciiCharacter {
+                    index: "SSH-2.0-rssh ".len() + comment.find(|c: char| !c.is_ascii()).unwrap(),
+                    value: format!("SSH-2.0-rssh {comment}\r\n"),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn encode_rejects_invalid_software_versions() {
+        for (software_version, reason) in [
+            ("my ssh", "\" \" is not allowed (found at index 2)"),
+            ("my-ssh", "\"-\" is not allowed (found at index 2)"),
+            (
+                "my\tssh",
+                "control character '\\t' is not allowed (found at index 2)",
+            ),
+            (
+                "ssh\x7f",
+                "control character '\\u{7f}' is not allowed (found at index 3)",
+            ),
+        ] {
+            let ident = Identification::new(SSHVersion::Ver2, software_version.into(), None);
+
+            assert_eq!(
+                ident.try_encode_to_string(),
+                Err(IdentificationError::InvalidSoftwareVersion {
+                    reason: reason.into()
+                }),
+                "{software_version:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn decode_rejects_non_ascii_comments() {
+        assert_eq!(
+            Identification::decode_from_string("SSH-2.0-rssh caf\u{e9}\r\n".into()),
+            Err(IdentificationError::NonAsciiCharacter {
+                index: 16,
+                value: "SSH-2.0-rssh caf\u{e9}\r\n".into(),
+            })
+        );
+
+        // Previously the multi-byte character caused a panic when slicing out the ending
+        assert_eq!(
+            Identification::decode_from_string("SSH-2.0-rssh \u{1F680}".into()),
+            Err(IdentificationError::NonAsciiCharacter {
+                index: 13,
+                value: "SSH-2.0-rssh \u{1F680}".into(),
+            })
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn async_identification_exchange() {
+        use tokio::io::{AsyncWriteExt, BufReader};
+
+        let (client, server) = tokio::io::duplex(64);
+        let (server_read, mut server_write) = tokio::io::split(server);
+
+        let client_ident = Identification::new(SSHVersion::Ver2, "client".into(), None);
+        let server_task = tokio::spawn(async move {
+            server_write.write_all(b"Hello\r\n").await.unwrap();
+            Identification::new(SSHVersion::Ver2, "server".into(), Some("hi".into()))
+                .write_to_async(&mut server_write)
+                .await
+                .unwrap();
+
+            Identification::read_from_async(&mut BufReader::new(server_read)).await
+        });
+
+        let (client_read, mut client_write) = tokio::io::split(client);
+        client_ident
+            .write_to_async(&mut client_write)
+            .await
+            .unwrap();
+        let (preamble, server_ident) =
+            Identification::read_from_async(&mut BufReader::new(client_read))
+                .await
+                .unwrap();
+
+        assert_eq!(preamble, ["Hello"]);
+        assert_eq!(server_ident.software_version, "server");
+        assert_eq!(server_ident.comments.as_deref(), Some("hi"));
+        assert_eq!(server_task.await.unwrap(), Ok((vec![], client_ident)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_skips_preamble_lines() {
+        let mut stream =
+            "Welcome to the server\r\nPlease behave\r\nSSH-2.0-rssh1.0 hello\r\nextra".as_bytes();
+
+        let (preamble, ident) = Identification::read_from(&mut stream).unwrap();
+
+        assert_eq!(preamble, vec!["Welcome to the server", "Please behave"]);
+        assert_eq!(
+            ident,
+            Identification::new(SSHVersion::Ver2, "rssh1.0".into(), Some("hello".into()))
+        );
+        // Data after the identification string must be left in the stream
+        assert_eq!(stream, b"extra");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_errors() {
+        assert_eq!(
+            Identification::read_from(&mut "just text\r\n".as_bytes()),
+            Err(IdentificationError::UnexpectedEndOfStream)
+        );
+
+        let long_line = "a".repeat(300);
+        assert_eq!(
+            Identification::read_from(&mut long_line.as_bytes()),
+            Err(IdentificationError::MaxLengthExceeded {
+                length: 256,
+                value: long_line[..256].into(),
+            })
+        );
+
+        let flood = "\r\n".repeat(MAX_PREAMBLE_LINES + 1);
+        assert_eq!(
+            Identification::read_from(&mut flood.as_bytes()),
+            Err(IdentificationError::TooManyPreambleLines {
+                limit: MAX_PREAMBLE_LINES
+            })
+        );
+    }
+
+    #[test]
+    fn getters() {
+        let ident =
+            Identification::decode_from_string(UBUNTU_OPENSSH_IDENTIFICATON_STRING.into()).unwrap();
+
+        assert_eq!(ident.protocol_version(), &SSHVersion::Ver2);
+        assert_eq!(ident.software_version(), "OpenSSH_7.6p1");
+        assert_eq!(ident.comments(), Some("Ubuntu-4ubuntu0.5"));
+    }
+
+    #[test]
+    fn strict_decoding_rejects_compatibility_version() {
+        assert_eq!(
+            Identification::decode_from_string("SSH-1.99-x\n".into()),
+            Ok(Identification::new(
+                SSHVersion::Ver1 { minor: 99 },
+                "x".into(),
+                None
+            ))
+        );
+        assert_eq!(
+            Identification::decode_from_string_strict("SSH-1.99-x\n".into()),
+            Err(IdentificationError::UnsupportedProtocolVersion { ver: "1.99".into() })
+        );
+
+        assert_eq!(
+            Identification::decode_from_string_strict("SSH-2.0-x\r\n".into()),
+            Ok(Identification::new(SSHVersion::Ver2, "x".into(), None))
+        );
+        assert!(matches!(
+            Identification::decode_from_string_strict("SSH-2.0-x\n".into()),
+            Err(IdentificationError::InvalidEnding { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_from_str() {
+        assert_eq!(
+            "SSH-2.0-rssh\r\n".parse::<Identification>(),
+            Ok(Identification::new(SSHVersion::Ver2, "rssh".into(), None))
+        );
+        assert_eq!(
+            UBUNTU_OPENSSH_IDENTIFICATON_STRING.parse::<Identification>(),
+            Identification::decode_from_string(UBUNTU_OPENSSH_IDENTIFICATON_STRING.into())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        for ident in [
+            Identification::new(SSHVersion::Ver2, "rssh".into(), Some("a comment".into())),
+            Identification::new(SSHVersion::Ver1 { minor: 99 }, "rssh".into(), None),
+        ] {
+            let json = serde_json::to_string(&ident).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Identification>(&json).unwrap(),
+                ident
+            );
+        }
+
+        assert_eq!(
+            serde_json::to_string(&SSHVersion::Ver1 { minor: 99 }).unwrap(),
+            r#"{"Ver1":{"minor":99}}"#
+        );
+
+        let error = IdentificationError::MissingSoftwareVersion { offset: 7 };
+        let json = serde_json::to_string(&error).unwrap();
+        assert_eq!(
+            serde_json::from_str::<IdentificationError>(&json).unwrap(),
+            error
+        );
+    }
+
+    #[test]
+    fn known_quirks() {
+        let old_openssh = Identification::new(SSHVersion::Ver2, "OpenSSH_2.5.2p2".into(), None);
+        assert_eq!(
+            old_openssh.known_quirks(),
+            vec![Quirk::OldDhGexSha1, Quirk::RekeyBug, Quirk::NoStrictKex]
+        );
+
+        let dropbear = Identification::new(SSHVersion::Ver2, "dropbear_0.46".into(), None);
+        assert_eq!(
+            dropbear.known_quirks(),
+            vec![Quirk::NoStrictKex, Quirk::RekeyBug]
+        );
+
+        let current = Identification::new(SSHVersion::Ver2, "OpenSSH_9.6".into(), None);
+        assert_eq!(current.known_quirks(), vec![]);
+    }
 }
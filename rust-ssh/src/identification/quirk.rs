@@ -0,0 +1,27 @@
+/// Known bugs or missing features in other ssh implementations that need to be worked around
+/// when talking to them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Quirk {
+    /// The peer doesn't support the strict key exchange extension, so the sequence numbers must
+    /// not be reset after a key exchange
+    NoStrictKex,
+    /// The peer uses the old (pre RFC 4419) diffie-hellman group exchange request message
+    OldDhGexSha1,
+    /// The peer can't handle a key exchange after the initial one, so the connection must never
+    /// be re-keyed
+    RekeyBug,
+}
+
+/// The software version substrings that trigger each quirk.
+///
+/// An `Identification` has a quirk if its software version contains the string in the first
+/// element of any entry, a single software version may match several entries.
+pub const KNOWN_QUIRKS: &[(&str, Quirk)] = &[
+    ("OpenSSH_2.3.", Quirk::OldDhGexSha1),
+    ("OpenSSH_2.3.", Quirk::RekeyBug),
+    ("OpenSSH_2.5.", Quirk::OldDhGexSha1),
+    ("OpenSSH_2.5.", Quirk::RekeyBug),
+    ("OpenSSH_2.", Quirk::NoStrictKex),
+    ("dropbear_0.4", Quirk::NoStrictKex),
+    ("dropbear_0.4", Quirk::RekeyBug),
+];
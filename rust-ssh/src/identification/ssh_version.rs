@@ -1,5 +1,6 @@
 /// IETF recognized ssh protocol version numbers.
-#[derive(Eq, Debug, PartialEq)]
+#[derive(Clone, Copy, Eq, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SSHVersion {
     /// The standard defined version, usually the one that should be used by any client or server
     Ver2,
@@ -9,9 +10,9 @@ pub enum SSHVersion {
     Ver1 { minor: usize },
 }
 
-impl std::fmt::Display for SSHVersion {
+impl core::fmt::Display for SSHVersion {
     /// Convert to a recognized string version of the version number
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
             Self::Ver2 => write!(f, "2.0"),
             Self::Ver1 { minor } => write!(f, "1.{minor}"),
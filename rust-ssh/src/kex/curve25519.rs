@@ -0,0 +1,202 @@
+use super::{compute_ecdh_exchange_hash, HashAlg, KexError, KexOutput};
+use crate::message::ecdh::{KexEcdhInit, KexEcdhReply};
+use crate::message::Message;
+use crate::prelude::*;
+
+use rand::{CryptoRng, RngCore};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+/// The client side of the `curve25519-sha256` key exchange from RFC 8731
+pub struct Curve25519Kex {
+    secret: StaticSecret,
+    public: PublicKey,
+}
+
+impl Curve25519Kex {
+    /// Generate a new ephemeral key pair using the operating system's random number generator
+    #[cfg(feature = "std")]
+    pub fn new() -> Self {
+        Self::with_rng(&mut rand::rngs::OsRng)
+    }
+
+    /// Generate a new ephemeral key pair from `rng`
+    pub fn with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut secret = [0; 32];
+        rng.fill_bytes(&mut secret);
+
+        Self::from_secret(secret)
+    }
+
+    /// Use a fixed private scalar, only useful for testing against known values
+    pub fn from_secret(secret: [u8; 32]) -> Self {
+        let secret = StaticSecret::from(secret);
+        let public = PublicKey::from(&secret);
+
+        Self { secret, public }
+    }
+
+    /// The ephemeral public key `Q_C` that is sent to the server
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public.to_bytes()
+    }
+
+    /// The payload of the `SSH_MSG_KEX_ECDH_INIT` message carrying our public key
+    pub fn init_payload(&self) -> Vec<u8> {
+        let init = KexEcdhInit {
+            client_public: self.public_key().to_vec(),
+        };
+
+        init.encode().expect("KEX_ECDH_INIT always encodes")
+    }
+
+    /// Finish the exchange using the payload of the server's `SSH_MSG_KEX_ECDH_REPLY`
+    ///
+    /// The identification strings and `KexInitMessage` payloads of both sides are needed to
+    /// compute the exchange hash. The host key signature in the output must still be verified
+    /// before the keys are used.
+    pub fn process_reply(
+        self,
+        client_ident: &str,
+        server_ident: &str,
+        client_kexinit: &[u8],
+        server_kexinit: &[u8],
+        reply: &[u8],
+    ) -> Result<KexOutput, KexError> {
+        let KexEcdhReply {
+            host_key,
+            server_public,
+            signature,
+        } = KexEcdhReply::decode(reply)?;
+
+        let server_public: [u8; 32] = match server_public.as_slice().try_into() {
+            Ok(server_public) => server_public,
+            Err(_) => {
+                return Err(KexError::InvalidPublicKey {
+                    len: server_public.len(),
+                })
+            }
+        };
+
+        let shared_secret = self.secret.diffie_hellman(&PublicKey::from(server_public));
+        if !shared_secret.was_contributory() {
+            return Err(KexError::ZeroSharedSecret);
+        }
+
+        // The shared secret is used as an unsigned big endian integer
+        let shared_secret = Zeroizing::new(shared_secret.as_bytes().to_vec());
+
+        let exchange_hash = compute_ecdh_exchange_hash(
+            HashAlg::Sha256,
+            client_ident,
+            server_ident,
+            client_kexinit,
+            server_kexinit,
+            &host_key,
+            &self.public_key(),
+            &server_public,
+            &shared_secret,
+        );
+
+        Ok(KexOutput {
+            hash: HashAlg::Sha256,
+            shared_secret,
+            exchange_hash,
+            host_key,
+            signature,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for Curve25519Kex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kex::*;
+
+    fn hex(value: &str) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        bytes
+    }
+
+    fn reply(server_public: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.put_u8(31);
+        writer.put_string(b"host key");
+        writer.put_string(server_public);
+        writer.put_string(b"signature");
+        writer.into_bytes()
+    }
+
+    #[test]
+    fn rfc_7748_shared_secret() {
+        let kex = Curve25519Kex::from_secret(hex(
+            "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+        ));
+
+        assert_eq!(
+            kex.public_key(),
+            hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(kex.init_payload()[..5], [30, 0, 0, 0, 32]);
+
+        let server_public = hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f");
+        let client_public = kex.public_key();
+
+        let output = kex
+            .process_reply(
+                "SSH-2.0-c",
+                "SSH-2.0-s",
+                &[20],
+                &[20],
+                &reply(&server_public),
+            )
+            .unwrap();
+
+        assert_eq!(
+            *output.shared_secret,
+            hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+        );
+        assert_eq!(output.host_key, b"host key");
+        assert_eq!(output.signature, b"signature");
+        assert_eq!(
+            output.exchange_hash,
+            compute_ecdh_exchange_hash(
+                HashAlg::Sha256,
+                "SSH-2.0-c",
+                "SSH-2.0-s",
+                &[20],
+                &[20],
+                b"host key",
+                &client_public,
+                &server_public,
+                &output.shared_secret,
+            )
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn invalid_server_keys() {
+        let kex = Curve25519Kex::new();
+        assert_eq!(
+            kex.process_reply("", "", &[], &[], &reply(&[9; 31])).err(),
+            Some(KexError::InvalidPublicKey { len: 31 })
+        );
+
+        // The identity point gives an all zero shared secret
+        let kex = Curve25519Kex::new();
+        assert_eq!(
+            kex.process_reply("", "", &[], &[], &reply(&[0; 32])).err(),
+            Some(KexError::ZeroSharedSecret)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use super::{compute_exchange_hash, HashAlg, KexError, KexOutput};
+use crate::message::dh::{KexDhInit, KexDhReply};
+use crate::message::Message;
+use crate::prelude::*;
+
+use crypto_bigint::modular::constant_mod::ResidueParams;
+use crypto_bigint::{const_residue, impl_modulus, Encoding, U2048, U512};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+// The 2048-bit MODP group from RFC 3526 section 3, with generator 2
+impl_modulus!(
+    Group14,
+    U2048,
+    concat!(
+        "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+        "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+        "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+        "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+        "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+        "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+        "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+        "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF",
+    )
+);
+
+/// Raise `base` to the power of the private exponent in the group
+fn pow_mod(base: &U2048, exponent: &U512) -> U2048 {
+    const_residue!(base, Group14)
+        .pow_bounded_exp(exponent, U512::BITS)
+        .retrieve()
+}
+
+/// The client side of the `diffie-hellman-group14-sha256` key exchange from RFC 8268
+///
+/// The private exponent is 512 bits, twice the security level of the group as recommended by
+/// RFC 8270.
+pub struct DhGroup14Kex {
+    secret: Zeroizing<U512>,
+    public: U2048,
+}
+
+impl DhGroup14Kex {
+    /// Generate a new private exponent using the operating system's random number generator
+    #[cfg(feature = "std")]
+    pub fn new() -> Self {
+        Self::with_rng(&mut rand::rngs::OsRng)
+    }
+
+    /// Generate a new private exponent from `rng`
+    pub fn with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut secret = Zeroizing::new([0; 64]);
+        rng.fill_bytes(secret.as_mut());
+        // Make sure the exponent isn't so small that the public value is easy to invert
+        secret[0] |= 0x80;
+
+        Self::from_secret(*secret)
+    }
+
+    /// Use a fixed private exponent, only useful for testing against known values
+    pub fn from_secret(secret: [u8; 64]) -> Self {
+        let secret = Zeroizing::new(U512::from_be_slice(&secret));
+        let public = pow_mod(&U2048::from_u8(2), &secret);
+
+        Self { secret, public }
+    }
+
+    /// The public value `e` that is sent to the server, as unsigned big endian bytes
+    pub fn public_value(&self) -> Vec<u8> {
+        self.public.to_be_bytes().to_vec()
+    }
+
+    /// The payload of the `SSH_MSG_KEXDH_INIT` message carrying our public value
+    pub fn init_payload(&self) -> Vec<u8> {
+        let init = KexDhInit {
+            e: self.public_value(),
+        };
+
+        init.encode().expect("KEXDH_INIT always encodes")
+    }
+
+    /// Finish the exchange using the payload of the server's `SSH_MSG_KEXDH_REPLY`, see
+    /// `Curve25519Kex::process_reply`
+    pub fn process_reply(
+        self,
+        client_ident: &str,
+        server_ident: &str,
+        client_kexinit: &[u8],
+        server_kexinit: &[u8],
+        reply: &[u8],
+    ) -> Result<KexOutput, KexError> {
+        let KexDhReply {
+            host_key,
+            f,
+            signature,
+        } = KexDhReply::decode(reply)?;
+
+        // Values outside of 1 < f < p - 1 would make the shared secret predictable
+        if f.len() > U2048::BYTES {
+            return Err(KexError::PublicValueOutOfRange);
+        }
+        let mut padded = [0; U2048::BYTES];
+        padded[U2048::BYTES - f.len()..].copy_from_slice(&f);
+        let f = U2048::from_be_slice(&padded);
+        if f <= U2048::ONE || f >= Group14::MODULUS.wrapping_sub(&U2048::ONE) {
+            return Err(KexError::PublicValueOutOfRange);
+        }
+
+        let shared_secret = Zeroizing::new(pow_mod(&f, &self.secret).to_be_bytes().to_vec());
+
+        let exchange_hash = compute_exchange_hash(
+            HashAlg::Sha256,
+            client_ident,
+            server_ident,
+            client_kexinit,
+            server_kexinit,
+            &host_key,
+            &self.public_value(),
+            &f.to_be_bytes(),
+            &shared_secret,
+        );
+
+        Ok(KexOutput {
+            hash: HashAlg::Sha256,
+            shared_secret,
+            exchange_hash,
+            host_key,
+            signature,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for DhGroup14Kex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::Writer;
+    use crate::kex::*;
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn reply(f: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.put_u8(31);
+        writer.put_string(b"host key");
+        writer.put_mpint(f);
+        writer.put_string(b"signature");
+        writer.into_bytes()
+    }
+
+    fn small_exponent(value: u32) -> DhGroup14Kex {
+        let mut secret = [0; 64];
+        secret[60..].copy_from_slice(&value.to_be_bytes());
+        DhGroup14Kex::from_secret(secret)
+    }
+
+    #[test]
+    fn shared_secret_known_answer() {
+        let kex = small_exponent(0x1234567);
+
+        // 2^x is small enough to check directly for a tiny exponent
+        let public = small_exponent(10).public_value();
+        assert_eq!(public[..254], [0; 254]);
+        assert_eq!(public[254..], [4, 0]);
+
+        // 3^0x1234567 mod p, computed independently
+        let expected = hex(concat!(
+            "eb1c002fd424b3506ab73c5e16bb334acb2102ee2ef715def69a3a17060f9622",
+            "c4c7a99dde7b2d4b5e61626d57fd0bf15c988b5b09c0e12d426dadcbab98d40b",
+            "abb765b9415b374d92d9167e686b190b41b74a15417804dc99086a87eeb4779f",
+            "e0d0262df3d73daaa21d4daca271d06668296bcfc8f0a7734c42b1642c86b2c8",
+            "ec2c7eacdc71f618c8a0ab33a2e564eac0df6dca2d862149db0c6725aa2084e4",
+            "e09be744ca5eb52d8196d107e737d9e1108734d5ff6a63652528b939d7ca936c",
+            "6cf2e80644d241c5d41bbd5503bc43c073346c88ae6a44faccae958b7e3fba1d",
+            "da2c1e68b180d5d5feadf989011b75373923a8b61f14dc2e6faa47c10e56e6e9",
+        ));
+
+        let client_public = kex.public_value();
+        let output = kex
+            .process_reply("SSH-2.0-c", "SSH-2.0-s", &[20], &[20], &reply(&[3]))
+            .unwrap();
+
+        assert_eq!(*output.shared_secret, expected);
+        assert_eq!(output.host_key, b"host key");
+        assert_eq!(
+            output.exchange_hash,
+            compute_exchange_hash(
+                HashAlg::Sha256,
+                "SSH-2.0-c",
+                "SSH-2.0-s",
+                &[20],
+                &[20],
+                b"host key",
+                &client_public,
+                &[3],
+                &expected,
+            )
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn both_sides_agree() {
+        let client = DhGroup14Kex::new();
+        let server = DhGroup14Kex::new();
+
+        let client_public = client.public_value();
+        let client_output = client
+            .process_reply("", "", &[], &[], &reply(&server.public_value()))
+            .unwrap();
+        let server_output = server
+            .process_reply("", "", &[], &[], &reply(&client_public))
+            .unwrap();
+
+        assert_eq!(client_output.shared_secret, server_output.shared_secret);
+    }
+
+    #[test]
+    fn public_values_out_of_range() {
+        let p_minus_one = hex(concat!(
+            "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74",
+            "020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F1437",
+            "4FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+            "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF05",
+            "98DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB",
+            "9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+            "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF695581718",
+            "3995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFE",
+        ));
+
+        for f in [&[][..], &[1], &p_minus_one, &[1; 257]] {
+            assert_eq!(
+                small_exponent(5)
+                    .process_reply("", "", &[], &[], &reply(f))
+                    .err(),
+                Some(KexError::PublicValueOutOfRange)
+            );
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::message::{DecodeError, MessageError};
+
+/// Possible errors during a key exchange
+#[derive(Debug, Eq, PartialEq)]
+pub enum KexError {
+    /// A key exchange message couldn't be decoded
+    Message(MessageError),
+    /// The other side's public key had the wrong length
+    ///
+    /// len: the length of the key that was received
+    InvalidPublicKey { len: usize },
+    /// The other side's Diffie-Hellman public value wasn't in the range `1 < f < p - 1`
+    PublicValueOutOfRange,
+    /// The shared secret was all zeroes, which happens when the other side sends a low order point
+    ZeroSharedSecret,
+}
+
+impl core::fmt::Display for KexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Message(e) => write!(f, "Invalid key exchange message: {e}"),
+            Self::InvalidPublicKey { len } => write!(f, "Invalid public key length {len}"),
+            Self::PublicValueOutOfRange => write!(f, "Public value is out of range for the group"),
+            Self::ZeroSharedSecret => write!(f, "Key exchange produced an all zero shared secret"),
+        }
+    }
+}
+
+impl core::error::Error for KexError {}
+
+impl From<MessageError> for KexError {
+    fn from(e: MessageError) -> Self {
+        Self::Message(e)
+    }
+}
+
+impl From<DecodeError> for KexError {
+    fn from(e: DecodeError) -> Self {
+        Self::Message(e.into())
+    }
+}
@@ -0,0 +1,310 @@
+//! Key exchange as defined by RFC 4253 sections 7 and 8
+
+mod curve25519;
+mod dh_group14;
+mod kex_error;
+
+pub use curve25519::*;
+pub use dh_group14::*;
+pub use kex_error::*;
+
+use crate::codec::Writer;
+use crate::message::KexAlgorithm;
+use crate::prelude::*;
+
+use sha2::Digest;
+use zeroize::Zeroizing;
+
+/// The key exchange methods that have a working implementation
+pub fn supported_kex() -> Vec<KexAlgorithm> {
+    vec![
+        KexAlgorithm::Curve25519Sha256,
+        KexAlgorithm::Curve25519Sha256Libssh,
+        KexAlgorithm::DiffieHellmanGroup14Sha256,
+    ]
+}
+
+/// The client side of whichever supported key exchange method was negotiated
+pub enum ClientKex {
+    Curve25519(Curve25519Kex),
+    DhGroup14(Box<DhGroup14Kex>),
+}
+
+impl ClientKex {
+    /// Start a key exchange for `algorithm`, or `None` if it isn't in `supported_kex`
+    #[cfg(feature = "std")]
+    pub fn new(algorithm: &KexAlgorithm) -> Option<Self> {
+        match algorithm {
+            KexAlgorithm::Curve25519Sha256 | KexAlgorithm::Curve25519Sha256Libssh => {
+                Some(Self::Curve25519(Curve25519Kex::new()))
+            }
+            KexAlgorithm::DiffieHellmanGroup14Sha256 => Some(Self::DhGroup14(Box::default())),
+            _ => None,
+        }
+    }
+
+    /// The payload of the first message the client sends
+    pub fn init_payload(&self) -> Vec<u8> {
+        match self {
+            Self::Curve25519(kex) => kex.init_payload(),
+            Self::DhGroup14(kex) => kex.init_payload(),
+        }
+    }
+
+    /// Finish the exchange using the payload of the server's reply
+    pub fn process_reply(
+        self,
+        client_ident: &str,
+        server_ident: &str,
+        client_kexinit: &[u8],
+        server_kexinit: &[u8],
+        reply: &[u8],
+    ) -> Result<KexOutput, KexError> {
+        match self {
+            Self::Curve25519(kex) => kex.process_reply(
+                client_ident,
+                server_ident,
+                client_kexinit,
+                server_kexinit,
+                reply,
+            ),
+            Self::DhGroup14(kex) => kex.process_reply(
+                client_ident,
+                server_ident,
+                client_kexinit,
+                server_kexinit,
+                reply,
+            ),
+        }
+    }
+}
+
+/// The hash function used by a key exchange method
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HashAlg {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashAlg {
+    /// Hash `data` with the algorithm
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => sha1::Sha1::digest(data).to_vec(),
+            Self::Sha256 => sha2::Sha256::digest(data).to_vec(),
+            Self::Sha512 => sha2::Sha512::digest(data).to_vec(),
+        }
+    }
+
+    /// The length of the hash in bytes
+    pub fn output_len(&self) -> usize {
+        match self {
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+            Self::Sha512 => 64,
+        }
+    }
+}
+
+/// Compute the exchange hash `H` for a Diffie-Hellman key exchange
+///
+/// The identification strings are given without the line ending and the `KexInitMessage`s as
+/// their full payloads. `e`, `f` and the shared secret are non-negative big endian integers that
+/// are encoded as mpints.
+#[allow(clippy::too_many_arguments)]
+pub fn compute_exchange_hash(
+    hash: HashAlg,
+    client_ident: &str,
+    server_ident: &str,
+    client_kexinit: &[u8],
+    server_kexinit: &[u8],
+    host_key: &[u8],
+    e: &[u8],
+    f: &[u8],
+    shared_secret: &[u8],
+) -> Vec<u8> {
+    let mut writer = Writer::new();
+
+    writer.put_string(client_ident.as_bytes());
+    writer.put_string(server_ident.as_bytes());
+    writer.put_string(client_kexinit);
+    writer.put_string(server_kexinit);
+    writer.put_string(host_key);
+    writer.put_mpint(e);
+    writer.put_mpint(f);
+    writer.put_mpint(shared_secret);
+
+    hash.hash(&writer.into_bytes())
+}
+
+/// Compute the exchange hash `H` for an elliptic curve Diffie-Hellman key exchange
+///
+/// This is the same as `compute_exchange_hash`, except that the public keys `Q_C` and `Q_S` are
+/// encoded as strings rather than mpints, as described in RFC 5656 section 4.
+#[allow(clippy::too_many_arguments)]
+pub fn compute_ecdh_exchange_hash(
+    hash: HashAlg,
+    client_ident: &str,
+    server_ident: &str,
+    client_kexinit: &[u8],
+    server_kexinit: &[u8],
+    host_key: &[u8],
+    client_public: &[u8],
+    server_public: &[u8],
+    shared_secret: &[u8],
+) -> Vec<u8> {
+    let mut writer = Writer::new();
+
+    writer.put_string(client_ident.as_bytes());
+    writer.put_string(server_ident.as_bytes());
+    writer.put_string(client_kexinit);
+    writer.put_string(server_kexinit);
+    writer.put_string(host_key);
+    writer.put_string(client_public);
+    writer.put_string(server_public);
+    writer.put_mpint(shared_secret);
+
+    hash.hash(&writer.into_bytes())
+}
+
+/// The result of a completed key exchange
+pub struct KexOutput {
+    /// The hash function of the key exchange method, also used to derive keys
+    pub hash: HashAlg,
+    /// The shared secret `K` as an unsigned big endian integer
+    pub shared_secret: Zeroizing<Vec<u8>>,
+    /// The exchange hash `H`, the first one of a connection is also the session id
+    pub exchange_hash: Vec<u8>,
+    /// The server's public host key blob
+    pub host_key: Vec<u8>,
+    /// The server's signature of the exchange hash, which must be verified with the host key
+    pub signature: Vec<u8>,
+}
+
+impl KexOutput {
+    /// Derive the keys for the new session, see `derive_keys`
+    pub fn derive_keys(&self, session_id: &[u8], key_len: usize) -> [Zeroizing<Vec<u8>>; 6] {
+        derive_keys(
+            self.hash,
+            &self.shared_secret,
+            &self.exchange_hash,
+            session_id,
+            key_len,
+        )
+    }
+}
+
+/// Derive the six keys used after a key exchange, as described in RFC 4253 section 7.2
+///
+/// The keys are returned in the order of the letters used to derive them: the client to server
+/// and server to client IVs, then the encryption keys, then the mac keys. Each one is extended
+/// with further hashes until it is `key_len` bytes long. They are cleared from memory when
+/// dropped, as is everything derived from the shared secret along the way.
+pub fn derive_keys(
+    hash: HashAlg,
+    shared_secret: &[u8],
+    exchange_hash: &[u8],
+    session_id: &[u8],
+    key_len: usize,
+) -> [Zeroizing<Vec<u8>>; 6] {
+    let mut writer = Writer::new();
+    writer.put_mpint(shared_secret);
+    writer.put_bytes(exchange_hash);
+    let prefix = Zeroizing::new(writer.into_bytes());
+
+    b"ABCDEF".map(|letter| {
+        let mut data = prefix.clone();
+        data.push(letter);
+        data.extend(session_id);
+
+        let mut key = Zeroizing::new(hash.hash(&data));
+
+        // K2 = HASH(K || H || K1), K3 = HASH(K || H || K1 || K2) and so on
+        while key.len() < key_len {
+            let mut data = prefix.clone();
+            data.extend(key.iter());
+            key.extend(hash.hash(&data));
+        }
+
+        key.truncate(key_len);
+        key
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kex::*;
+
+    #[test]
+    fn exchange_hash_known_answer() {
+        // Computed independently by hashing the RFC 4253 encoding of the same inputs
+        let exchange_hash = compute_exchange_hash(
+            HashAlg::Sha256,
+            "SSH-2.0-client",
+            "SSH-2.0-server",
+            &[20, 1, 2, 3],
+            &[20, 4, 5, 6],
+            b"host key",
+            &[0x01, 0x02],
+            &[0x80, 0x01],
+            &[0x00, 0x7f, 0xff],
+        );
+
+        assert_eq!(
+            exchange_hash,
+            [
+                0xc5, 0xc4, 0x74, 0x02, 0x49, 0x09, 0xc2, 0x8d, 0xf8, 0x11, 0xaf, 0xde, 0x46, 0x8d,
+                0xe3, 0xf0, 0xef, 0x75, 0x46, 0x3b, 0xe8, 0xb1, 0xf3, 0x7a, 0x50, 0xdd, 0x37, 0xcb,
+                0x43, 0xbe, 0xbd, 0x98,
+            ]
+        );
+    }
+
+    #[test]
+    fn derived_key_lengths() {
+        for key_len in [12, 16, 32, 64, 100] {
+            let keys = derive_keys(HashAlg::Sha256, &[1, 2, 3], &[4; 32], &[5; 32], key_len);
+
+            assert!(keys.iter().all(|key| key.len() == key_len));
+        }
+
+        // Every letter gives a different key
+        let keys = derive_keys(HashAlg::Sha1, &[1, 2, 3], &[4; 20], &[5; 20], 16);
+        for (i, key) in keys.iter().enumerate() {
+            assert!(keys[i + 1..].iter().all(|other| other != key));
+        }
+    }
+
+    #[test]
+    fn derived_keys_extend_past_one_hash() {
+        let shared_secret = [0x80, 0x01];
+        let exchange_hash = [4; 32];
+
+        let short = derive_keys(
+            HashAlg::Sha256,
+            &shared_secret,
+            &exchange_hash,
+            &[5; 32],
+            32,
+        );
+        let long = derive_keys(
+            HashAlg::Sha256,
+            &shared_secret,
+            &exchange_hash,
+            &[5; 32],
+            64,
+        );
+
+        let mut prefix = b"\0\0\0\x03\0\x80\x01".to_vec();
+        prefix.extend(exchange_hash);
+
+        for (short, long) in short.iter().zip(long.iter()) {
+            assert_eq!(long[..32], short[..]);
+
+            let mut data = prefix.clone();
+            data.extend(short.iter());
+            assert_eq!(long[32..], HashAlg::Sha256.hash(&data));
+        }
+    }
+}
@@ -1,13 +1,40 @@
-#![feature(box_syntax)]
-#![feature(box_patterns)]
-#![feature(iter_intersperse)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Without the default `std` feature only `alloc` is needed, which leaves out the transport
+//! layer and anything else that does I/O or needs the operating system's random number
+//! generator.
+
+extern crate alloc;
+
+/// The parts of the standard prelude that live in `alloc`, so that modules can use them the same
+/// way with or without `std`
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use alloc::{format, vec};
+}
+
+mod rssh_error;
+
+pub use rssh_error::*;
 
 pub mod utils;
 
+pub mod codec;
+
 pub mod identification;
 
+pub mod compression;
 pub mod message;
 pub mod packet;
 
 pub mod encryption;
+pub mod hostkey;
+pub mod kex;
 pub mod mac;
+
+#[cfg(any(feature = "test-util", all(test, feature = "std")))]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod transport;
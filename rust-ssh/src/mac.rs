@@ -1,9 +0,0 @@
-pub enum Mac {
-    HmacSha1,
-    HmacSha1_96,
-
-    HmacMD5,
-    HmacMD5_96,
-
-    None,
-}
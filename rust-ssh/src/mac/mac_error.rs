@@ -0,0 +1,24 @@
+/// Possible errors when checking the mac received with a packet
+#[derive(Debug, Eq, PartialEq)]
+pub enum MacError {
+    /// The received tag was a different length than the mac produces
+    ///
+    /// expected: the length of the tag the mac produces
+    /// actual: the length of the tag that was received
+    InvalidTagLength { expected: usize, actual: usize },
+    /// The received tag didn't match the packet
+    InvalidTag,
+}
+
+impl core::fmt::Display for MacError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::InvalidTagLength { expected, actual } => {
+                write!(f, "Expected a mac of {expected} bytes, got {actual}")
+            }
+            Self::InvalidTag => write!(f, "The mac didn't match the packet"),
+        }
+    }
+}
+
+impl core::error::Error for MacError {}
@@ -0,0 +1,356 @@
+mod mac_error;
+
+pub use mac_error::*;
+
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::digest::{Digest, KeyInit};
+use hmac::{Mac as _, SimpleHmac};
+use subtle::ConstantTimeEq;
+
+use crate::prelude::*;
+
+/// The message authentication codes that can be negotiated in a `KexInitMessage`
+///
+/// Names this crate doesn't know about are kept as `Unknown`, so that a peer's offer can be
+/// decoded and passed on unchanged.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Mac {
+    HmacSha1,
+    HmacSha1_96,
+
+    HmacSha256,
+    HmacSha512,
+    /// hmac-sha2-256 using OpenSSH's encrypt-then-mac packet layout
+    HmacSha256Etm,
+
+    /// hmac-md5 and hmac-md5-96 are only available with the `legacy-macs` feature
+    #[cfg(feature = "legacy-macs")]
+    HmacMD5,
+    #[cfg(feature = "legacy-macs")]
+    HmacMD5_96,
+
+    None,
+    /// A mac this crate doesn't know about, it can't compute or verify any tags
+    Unknown(String),
+}
+
+impl Mac {
+    /// The name of the mac as used in algorithm negotiation
+    pub fn name(&self) -> &str {
+        match self {
+            Self::HmacSha1 => "hmac-sha1",
+            Self::HmacSha1_96 => "hmac-sha1-96",
+            Self::HmacSha256 => "hmac-sha2-256",
+            Self::HmacSha512 => "hmac-sha2-512",
+            Self::HmacSha256Etm => "hmac-sha2-256-etm@openssh.com",
+            #[cfg(feature = "legacy-macs")]
+            Self::HmacMD5 => "hmac-md5",
+            #[cfg(feature = "legacy-macs")]
+            Self::HmacMD5_96 => "hmac-md5-96",
+            Self::None => "none",
+            Self::Unknown(name) => name,
+        }
+    }
+
+    /// The length of the key the mac is used with, which is `None` for macs that can't be
+    /// used to authenticate packets
+    pub fn key_len(&self) -> Option<usize> {
+        match self {
+            Self::HmacSha1 | Self::HmacSha1_96 => Some(20),
+            Self::HmacSha256 | Self::HmacSha256Etm => Some(32),
+            Self::HmacSha512 => Some(64),
+            #[cfg(feature = "legacy-macs")]
+            Self::HmacMD5 | Self::HmacMD5_96 => Some(16),
+            Self::None | Self::Unknown(_) => None,
+        }
+    }
+
+    /// Whether the mac is computed over the encrypted packet (encrypt-then-mac) rather than the
+    /// plaintext
+    pub fn is_etm(&self) -> bool {
+        matches!(self, Self::HmacSha256Etm)
+    }
+
+    /// Check a tag received with a packet against the one computed for it
+    ///
+    /// The tags are compared in constant time so that the comparison doesn't leak how much of a
+    /// forged tag was right. Always use this rather than comparing the output of `compute`.
+    pub fn verify(
+        &self,
+        key: &[u8],
+        sequence_number: u32,
+        packet: &[u8],
+        received_tag: &[u8],
+    ) -> bool {
+        // There is no tag to compare against, so nothing is accepted
+        if let Self::Unknown(_) = self {
+            return false;
+        }
+
+        let expected = self.compute(key, sequence_number, packet);
+
+        expected.ct_eq(received_tag).into()
+    }
+
+    /// Check a received tag like `verify`, returning why it was rejected
+    pub fn check(
+        &self,
+        key: &[u8],
+        sequence_number: u32,
+        packet: &[u8],
+        received_tag: &[u8],
+    ) -> Result<(), MacError> {
+        if received_tag.len() != self.output_len() {
+            return Err(MacError::InvalidTagLength {
+                expected: self.output_len(),
+                actual: received_tag.len(),
+            });
+        }
+
+        match self.verify(key, sequence_number, packet, received_tag) {
+            true => Ok(()),
+            false => Err(MacError::InvalidTag),
+        }
+    }
+}
+
+impl core::fmt::Display for Mac {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl core::str::FromStr for Mac {
+    type Err = core::convert::Infallible;
+
+    /// Parse the name of a mac as used in algorithm negotiation, unrecognized names give
+    /// `Mac::Unknown`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "hmac-sha1" => Self::HmacSha1,
+            "hmac-sha1-96" => Self::HmacSha1_96,
+            "hmac-sha2-256" => Self::HmacSha256,
+            "hmac-sha2-512" => Self::HmacSha512,
+            "hmac-sha2-256-etm@openssh.com" => Self::HmacSha256Etm,
+            #[cfg(feature = "legacy-macs")]
+            "hmac-md5" => Self::HmacMD5,
+            #[cfg(feature = "legacy-macs")]
+            "hmac-md5-96" => Self::HmacMD5_96,
+            "none" => Self::None,
+            name => Self::Unknown(name.into()),
+        })
+    }
+}
+
+/// The macs that have a working implementation
+pub fn supported_macs() -> Vec<Mac> {
+    vec![
+        Mac::HmacSha256Etm,
+        Mac::HmacSha256,
+        Mac::HmacSha512,
+        Mac::HmacSha1,
+        Mac::HmacSha1_96,
+        #[cfg(feature = "legacy-macs")]
+        Mac::HmacMD5,
+        #[cfg(feature = "legacy-macs")]
+        Mac::HmacMD5_96,
+        Mac::None,
+    ]
+}
+
+/// Computes the message authentication code appended to each packet
+pub trait Authenticator {
+    /// Compute the tag for a packet as `MAC(key, sequence_number || packet)`
+    fn compute(&self, key: &[u8], sequence_number: u32, packet: &[u8]) -> Vec<u8>;
+
+    /// The length of the tag appended to each packet
+    fn output_len(&self) -> usize;
+}
+
+/// Compute a HMAC over the sequence number and packet with the given hash function
+fn hmac<D>(key: &[u8], sequence_number: u32, packet: &[u8]) -> Vec<u8>
+where
+    D: Digest + BlockSizeUser,
+{
+    let mut mac =
+        <SimpleHmac<D> as KeyInit>::new_from_slice(key).expect("HMAC accepts keys of any length");
+
+    mac.update(&sequence_number.to_be_bytes());
+    mac.update(packet);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl Authenticator for Mac {
+    fn compute(&self, key: &[u8], sequence_number: u32, packet: &[u8]) -> Vec<u8> {
+        let mut tag = match self {
+            Self::HmacSha1 | Self::HmacSha1_96 => hmac::<sha1::Sha1>(key, sequence_number, packet),
+            Self::HmacSha256 | Self::HmacSha256Etm => {
+                hmac::<sha2::Sha256>(key, sequence_number, packet)
+            }
+            Self::HmacSha512 => hmac::<sha2::Sha512>(key, sequence_number, packet),
+            #[cfg(feature = "legacy-macs")]
+            Self::HmacMD5 | Self::HmacMD5_96 => hmac::<md5::Md5>(key, sequence_number, packet),
+            Self::None | Self::Unknown(_) => vec![],
+        };
+
+        // The truncated variants still compute the full HMAC and only send the start of it
+        tag.truncate(self.output_len());
+        tag
+    }
+
+    fn output_len(&self) -> usize {
+        match self {
+            Self::HmacSha1 => 20,
+            Self::HmacSha256 | Self::HmacSha256Etm => 32,
+            Self::HmacSha512 => 64,
+            #[cfg(feature = "legacy-macs")]
+            Self::HmacMD5 => 16,
+            Self::HmacSha1_96 => 12,
+            #[cfg(feature = "legacy-macs")]
+            Self::HmacMD5_96 => 12,
+            Self::None | Self::Unknown(_) => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mac::*;
+
+    #[test]
+    fn supported_macs_compute_tags() {
+        for mac in supported_macs() {
+            assert_eq!(mac.compute(b"key", 0, b"packet").len(), mac.output_len());
+        }
+    }
+
+    /// The tag for RFC 2202 and RFC 4231 test case 2, where the first four bytes of the data
+    /// are used as the sequence number
+    fn test_case_2(mac: Mac) -> Vec<u8> {
+        let data = b"what do ya want for nothing?";
+        let sequence_number = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+
+        mac.compute(b"Jefe", sequence_number, &data[4..])
+    }
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hmac_sha1_known_answer() {
+        let full = hex("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+
+        assert_eq!(test_case_2(Mac::HmacSha1), full);
+        // The truncated variant sends the first 12 bytes of the same HMAC
+        assert_eq!(test_case_2(Mac::HmacSha1_96), full[..12]);
+    }
+
+    #[test]
+    fn hmac_sha512_known_answer() {
+        assert_eq!(
+            test_case_2(Mac::HmacSha512),
+            hex(concat!(
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554",
+                "9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+            ))
+        );
+    }
+
+    #[cfg(feature = "legacy-macs")]
+    #[test]
+    fn hmac_md5_known_answer() {
+        let full = hex("750c783e6ab0b503eaa86e310a5db738");
+
+        assert_eq!(test_case_2(Mac::HmacMD5), full);
+        assert_eq!(test_case_2(Mac::HmacMD5_96), full[..12]);
+    }
+
+    #[test]
+    fn hmac_sha256_known_answer() {
+        assert_eq!(
+            test_case_2(Mac::HmacSha256),
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+
+    #[test]
+    fn verify_tags() {
+        let tag = Mac::HmacSha256.compute(b"key", 7, b"packet");
+        assert!(Mac::HmacSha256.verify(b"key", 7, b"packet", &tag));
+
+        let mut forged = tag.clone();
+        forged[31] ^= 1;
+        assert!(!Mac::HmacSha256.verify(b"key", 7, b"packet", &forged));
+        assert!(!Mac::HmacSha256.verify(b"key", 8, b"packet", &tag));
+        assert!(!Mac::HmacSha256.verify(b"key", 7, b"packet", &tag[..16]));
+        assert!(!Mac::HmacSha256.verify(b"key", 7, b"packet", &[]));
+    }
+
+    #[test]
+    fn check_reports_why_tags_are_rejected() {
+        let tag = Mac::HmacSha1.compute(b"key", 7, b"packet");
+        assert_eq!(Mac::HmacSha1.check(b"key", 7, b"packet", &tag), Ok(()));
+        assert_eq!(
+            Mac::HmacSha1.check(b"key", 8, b"packet", &tag),
+            Err(MacError::InvalidTag)
+        );
+        assert_eq!(
+            Mac::HmacSha1.check(b"key", 7, b"packet", &tag[..12]),
+            Err(MacError::InvalidTagLength {
+                expected: 20,
+                actual: 12
+            })
+        );
+    }
+
+    #[test]
+    fn output_lengths() {
+        for mac in [
+            Mac::HmacSha1,
+            Mac::HmacSha1_96,
+            Mac::HmacSha256,
+            Mac::HmacSha512,
+            Mac::HmacSha256Etm,
+            #[cfg(feature = "legacy-macs")]
+            Mac::HmacMD5,
+            #[cfg(feature = "legacy-macs")]
+            Mac::HmacMD5_96,
+            Mac::None,
+        ] {
+            assert_eq!(mac.compute(b"key", 0, b"packet").len(), mac.output_len());
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for mac in supported_macs() {
+            assert_eq!(mac.to_string().parse(), Ok(mac));
+        }
+
+        assert_eq!(
+            "umac-64@openssh.com".parse(),
+            Ok(Mac::Unknown("umac-64@openssh.com".into()))
+        );
+        assert_eq!(
+            Mac::Unknown("umac-64@openssh.com".into()).to_string(),
+            "umac-64@openssh.com"
+        );
+    }
+
+    #[test]
+    fn unknown_macs_reject_every_tag() {
+        let mac = Mac::Unknown("umac-64@openssh.com".into());
+
+        assert!(!mac.verify(b"key", 0, b"packet", &[]));
+        assert_eq!(
+            mac.check(b"key", 0, b"packet", &[]),
+            Err(MacError::InvalidTag)
+        );
+        assert_eq!(mac.key_len(), None);
+    }
+}
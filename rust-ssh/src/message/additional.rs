@@ -0,0 +1,201 @@
+use super::{message_type::MessageType, take_message_type, Message, MessageError};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+use rand::{CryptoRng, Rng, RngCore};
+
+/// The `SSH_MSG_IGNORE` message, which must be ignored by the receiver
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IgnoreMessage {
+    pub data: Vec<u8>,
+}
+
+impl IgnoreMessage {
+    /// An ignore message with up to `max_len` random bytes, using the operating system's random
+    /// number generator
+    ///
+    /// Sending these between real messages makes it harder to tell what is being sent from the
+    /// sizes and timing of packets.
+    #[cfg(feature = "std")]
+    pub fn with_random_payload(max_len: usize) -> Self {
+        Self::with_random_payload_from_rng(&mut rand::rngs::OsRng, max_len)
+    }
+
+    /// An ignore message with up to `max_len` random bytes from `rng`
+    pub fn with_random_payload_from_rng<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        max_len: usize,
+    ) -> Self {
+        let mut data = vec![0; rng.gen_range(0..=max_len)];
+        rng.fill_bytes(&mut data);
+
+        Self { data }
+    }
+}
+
+impl Message for IgnoreMessage {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::Ignore
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::Ignore)?;
+        let ignored = reader.get_string()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            data: ignored.to_vec(),
+        })
+    }
+}
+
+/// The `SSH_MSG_DEBUG` message, used to send information that may help with debugging
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebugMessage {
+    /// Whether the message should be shown to the user even when debugging isn't enabled
+    pub always_display: bool,
+    pub message: String,
+    /// The RFC 3066 language tag of the message, usually empty
+    pub language_tag: String,
+}
+
+impl Message for DebugMessage {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_bool(self.always_display);
+        writer.put_string(self.message.as_bytes());
+        writer.put_string(self.language_tag.as_bytes());
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::Debug
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::Debug)?;
+        let always_display = reader.get_bool()?;
+        let message = reader.get_utf8()?;
+        let language_tag = reader.get_utf8()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            always_display,
+            message,
+            language_tag,
+        })
+    }
+}
+
+/// The `SSH_MSG_UNIMPLEMENTED` message, the response to a message with an unrecognized type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnimplementedMessage {
+    /// The sequence number of the packet that wasn't recognized
+    pub sequence_number: u32,
+}
+
+impl Message for UnimplementedMessage {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.sequence_number);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::Unimplemented
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::Unimplemented)?;
+        let sequence_number = reader.get_u32()?;
+        reader.expect_end()?;
+
+        Ok(Self { sequence_number })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::additional::*;
+
+    #[test]
+    fn ignore_message_round_trip() {
+        let message = IgnoreMessage {
+            data: vec![0xde, 0xad],
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x02\0\0\0\x02\xde\xad".to_vec());
+        assert_eq!(IgnoreMessage::decode(&encoded).unwrap(), message);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ignore_message_random_payload() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        for max_len in [0, 1, 16, 300] {
+            assert!(IgnoreMessage::with_random_payload(max_len).data.len() <= max_len);
+        }
+
+        // The length varies as well as the contents
+        let mut rng = StdRng::seed_from_u64(0);
+        let lengths: Vec<_> = (0..16)
+            .map(|_| {
+                IgnoreMessage::with_random_payload_from_rng(&mut rng, 64)
+                    .data
+                    .len()
+            })
+            .collect();
+        assert!(lengths.iter().any(|&len| len != lengths[0]));
+    }
+
+    #[test]
+    fn debug_message_round_trip() {
+        let message = DebugMessage {
+            always_display: true,
+            message: "hi".into(),
+            language_tag: "en".into(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x04\x01\0\0\0\x02hi\0\0\0\x02en".to_vec());
+        assert_eq!(DebugMessage::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn unimplemented_message_round_trip() {
+        let message = UnimplementedMessage {
+            sequence_number: 0x01020304,
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x03\x01\x02\x03\x04".to_vec());
+        assert_eq!(UnimplementedMessage::decode(&encoded).unwrap(), message);
+        assert!(UnimplementedMessage::decode(b"\x03\x01\x02\x03\x04\x05").is_err());
+    }
+}
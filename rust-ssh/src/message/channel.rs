@@ -0,0 +1,598 @@
+use super::{message_type::MessageType, take_message_type, Message, MessageError};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// The `SSH_MSG_CHANNEL_OPEN` message, sent to open a new channel as defined by RFC 4254
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelOpen {
+    /// The kind of channel to open, such as `session`
+    pub channel_type: String,
+    /// The number the sender uses to refer to the channel
+    pub sender_channel: u32,
+    /// How many bytes may be sent before the window must be adjusted
+    pub initial_window_size: u32,
+    /// The largest data packet the sender will accept
+    pub maximum_packet_size: u32,
+    /// Any additional fields specific to the channel type, already encoded
+    pub data: Vec<u8>,
+}
+
+impl Message for ChannelOpen {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(self.channel_type.as_bytes());
+        writer.put_u32(self.sender_channel);
+        writer.put_u32(self.initial_window_size);
+        writer.put_u32(self.maximum_packet_size);
+        writer.put_bytes(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelOpen
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelOpen)?;
+
+        Ok(Self {
+            channel_type: reader.get_utf8()?,
+            sender_channel: reader.get_u32()?,
+            initial_window_size: reader.get_u32()?,
+            maximum_packet_size: reader.get_u32()?,
+            data: reader.remaining().to_vec(),
+        })
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_OPEN_CONFIRMATION` message, sent when a channel was opened successfully
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelOpenConfirmation {
+    /// The channel number from the `ChannelOpen` message
+    pub recipient_channel: u32,
+    /// The number the sender uses to refer to the channel
+    pub sender_channel: u32,
+    pub initial_window_size: u32,
+    pub maximum_packet_size: u32,
+    /// Any additional fields specific to the channel type, already encoded
+    pub data: Vec<u8>,
+}
+
+impl Message for ChannelOpenConfirmation {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+        writer.put_u32(self.sender_channel);
+        writer.put_u32(self.initial_window_size);
+        writer.put_u32(self.maximum_packet_size);
+        writer.put_bytes(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelOpenConfirmation
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelOpenConfirmation)?;
+
+        Ok(Self {
+            recipient_channel: reader.get_u32()?,
+            sender_channel: reader.get_u32()?,
+            initial_window_size: reader.get_u32()?,
+            maximum_packet_size: reader.get_u32()?,
+            data: reader.remaining().to_vec(),
+        })
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_WINDOW_ADJUST` message, allowing the other side to send more data
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelWindowAdjust {
+    pub recipient_channel: u32,
+    pub bytes_to_add: u32,
+}
+
+impl Message for ChannelWindowAdjust {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+        writer.put_u32(self.bytes_to_add);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelWindowAdjust
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelWindowAdjust)?;
+        let recipient_channel = reader.get_u32()?;
+        let bytes_to_add = reader.get_u32()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            recipient_channel,
+            bytes_to_add,
+        })
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_DATA` message, carrying data over an open channel
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelData {
+    pub recipient_channel: u32,
+    pub data: Vec<u8>,
+}
+
+impl Message for ChannelData {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+        writer.put_string(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelData
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelData)?;
+        let recipient_channel = reader.get_u32()?;
+        let data = reader.get_string()?.to_vec();
+        reader.expect_end()?;
+
+        Ok(Self {
+            recipient_channel,
+            data,
+        })
+    }
+}
+
+/// The kind of data carried by a `ChannelExtendedData` message
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExtendedDataType {
+    /// `SSH_EXTENDED_DATA_STDERR`, the standard error of a command
+    Stderr,
+    /// A data type code that isn't defined by RFC 4254
+    Other(u32),
+}
+
+impl From<u32> for ExtendedDataType {
+    fn from(code: u32) -> Self {
+        match code {
+            1 => Self::Stderr,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<ExtendedDataType> for u32 {
+    fn from(data_type: ExtendedDataType) -> Self {
+        match data_type {
+            ExtendedDataType::Stderr => 1,
+            ExtendedDataType::Other(code) => code,
+        }
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_EXTENDED_DATA` message, carrying a separate stream of data over an open
+/// channel such as the standard error of a command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelExtendedData {
+    pub recipient_channel: u32,
+    pub data_type_code: ExtendedDataType,
+    pub data: Vec<u8>,
+}
+
+impl Message for ChannelExtendedData {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+        writer.put_u32(self.data_type_code.into());
+        writer.put_string(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelExtendedData
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelExtendedData)?;
+        let recipient_channel = reader.get_u32()?;
+        let data_type_code = reader.get_u32()?.into();
+        let data = reader.get_string()?.to_vec();
+        reader.expect_end()?;
+
+        Ok(Self {
+            recipient_channel,
+            data_type_code,
+            data,
+        })
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_EOF` message, sent when no more data will be sent on a channel
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelEof {
+    pub recipient_channel: u32,
+}
+
+impl Message for ChannelEof {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelEOF
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelEOF)?;
+        let recipient_channel = reader.get_u32()?;
+        reader.expect_end()?;
+
+        Ok(Self { recipient_channel })
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_CLOSE` message, sent when a channel is being closed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelClose {
+    pub recipient_channel: u32,
+}
+
+impl Message for ChannelClose {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelClose
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelClose)?;
+        let recipient_channel = reader.get_u32()?;
+        reader.expect_end()?;
+
+        Ok(Self { recipient_channel })
+    }
+}
+
+/// The type of a `ChannelRequest` along with the fields specific to it, from RFC 4254 section 6
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ChannelRequestType {
+    /// Allocate a pseudo-terminal for the session
+    PtyReq {
+        /// The value of the `TERM` environment variable, such as `xterm`
+        term: String,
+        /// The terminal width in characters
+        cols: u32,
+        /// The terminal height in rows
+        rows: u32,
+        width_px: u32,
+        height_px: u32,
+        /// The encoded terminal modes
+        modes: Vec<u8>,
+    },
+    /// Run a command
+    Exec { command: String },
+    /// Start the user's default shell
+    Shell,
+    /// Start a subsystem such as `sftp`
+    Subsystem { name: String },
+    /// A request type this crate doesn't know about, with its fields left encoded
+    Unknown { name: String, data: Vec<u8> },
+}
+
+impl ChannelRequestType {
+    /// The request type name sent over the wire
+    pub fn name(&self) -> &str {
+        match self {
+            Self::PtyReq { .. } => "pty-req",
+            Self::Exec { .. } => "exec",
+            Self::Shell => "shell",
+            Self::Subsystem { .. } => "subsystem",
+            Self::Unknown { name, .. } => name,
+        }
+    }
+}
+
+/// The `SSH_MSG_CHANNEL_REQUEST` message, used to start a program or change a channel
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelRequest {
+    pub recipient_channel: u32,
+    pub request_type: ChannelRequestType,
+    /// Whether a `ChannelSuccess` or `ChannelFailure` response should be sent
+    pub want_reply: bool,
+}
+
+impl Message for ChannelRequest {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.recipient_channel);
+        writer.put_string(self.request_type.name().as_bytes());
+        writer.put_bool(self.want_reply);
+
+        match &self.request_type {
+            ChannelRequestType::PtyReq {
+                term,
+                cols,
+                rows,
+                width_px,
+                height_px,
+                modes,
+            } => {
+                writer.put_string(term.as_bytes());
+                writer.put_u32(*cols);
+                writer.put_u32(*rows);
+                writer.put_u32(*width_px);
+                writer.put_u32(*height_px);
+                writer.put_string(modes);
+            }
+            ChannelRequestType::Exec { command } => writer.put_string(command.as_bytes()),
+            ChannelRequestType::Shell => {}
+            ChannelRequestType::Subsystem { name } => writer.put_string(name.as_bytes()),
+            ChannelRequestType::Unknown { data, .. } => writer.put_bytes(data),
+        }
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ChannelRequest
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ChannelRequest)?;
+        let recipient_channel = reader.get_u32()?;
+        let name = reader.get_utf8()?;
+        let want_reply = reader.get_bool()?;
+
+        let request_type = match name.as_str() {
+            "pty-req" => ChannelRequestType::PtyReq {
+                term: reader.get_utf8()?,
+                cols: reader.get_u32()?,
+                rows: reader.get_u32()?,
+                width_px: reader.get_u32()?,
+                height_px: reader.get_u32()?,
+                modes: reader.get_string()?.to_vec(),
+            },
+            "exec" => ChannelRequestType::Exec {
+                command: reader.get_utf8()?,
+            },
+            "shell" => ChannelRequestType::Shell,
+            "subsystem" => ChannelRequestType::Subsystem {
+                name: reader.get_utf8()?,
+            },
+            _ => ChannelRequestType::Unknown {
+                name,
+                data: reader.get_bytes(reader.remaining().len())?.to_vec(),
+            },
+        };
+
+        reader.expect_end()?;
+
+        Ok(Self {
+            recipient_channel,
+            request_type,
+            want_reply,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::channel::*;
+
+    #[test]
+    fn channel_open_round_trip() {
+        let message = ChannelOpen {
+            channel_type: "session".into(),
+            sender_channel: 1,
+            initial_window_size: 0x200000,
+            maximum_packet_size: 0x8000,
+            data: vec![],
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(
+            encoded,
+            b"\x5a\0\0\0\x07session\0\0\0\x01\0\x20\0\0\0\0\x80\0".to_vec()
+        );
+        assert_eq!(ChannelOpen::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn channel_open_confirmation_round_trip() {
+        let message = ChannelOpenConfirmation {
+            recipient_channel: 1,
+            sender_channel: 2,
+            initial_window_size: 3,
+            maximum_packet_size: 4,
+            data: vec![5, 6],
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(ChannelOpenConfirmation::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn channel_data_round_trip() {
+        let message = ChannelData {
+            recipient_channel: 7,
+            data: b"ls\n".to_vec(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x5e\0\0\0\x07\0\0\0\x03ls\n".to_vec());
+        assert_eq!(ChannelData::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn channel_extended_data_round_trip() {
+        let message = ChannelExtendedData {
+            recipient_channel: 7,
+            data_type_code: ExtendedDataType::Stderr,
+            data: b"oops\n".to_vec(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(
+            encoded,
+            b"\x5f\0\0\0\x07\0\0\0\x01\0\0\0\x05oops\n".to_vec()
+        );
+        assert_eq!(ChannelExtendedData::decode(&encoded).unwrap(), message);
+
+        let mut other = encoded.clone();
+        other[8] = 2;
+        assert_eq!(
+            ChannelExtendedData::decode(&other).unwrap().data_type_code,
+            ExtendedDataType::Other(2)
+        );
+    }
+
+    #[test]
+    fn channel_control_round_trip() {
+        let adjust = ChannelWindowAdjust {
+            recipient_channel: 1,
+            bytes_to_add: 1024,
+        };
+        assert_eq!(
+            ChannelWindowAdjust::decode(&adjust.encode().unwrap()).unwrap(),
+            adjust
+        );
+
+        let eof = ChannelEof {
+            recipient_channel: 1,
+        };
+        assert_eq!(eof.encode().unwrap(), [96, 0, 0, 0, 1]);
+        assert_eq!(ChannelEof::decode(&eof.encode().unwrap()).unwrap(), eof);
+
+        let close = ChannelClose {
+            recipient_channel: 1,
+        };
+        assert_eq!(close.encode().unwrap(), [97, 0, 0, 0, 1]);
+        assert_eq!(
+            ChannelClose::decode(&close.encode().unwrap()).unwrap(),
+            close
+        );
+
+        // Each message checks its own type byte
+        assert!(ChannelClose::decode(&eof.encode().unwrap()).is_err());
+    }
+
+    #[test]
+    fn channel_request_exec_round_trip() {
+        let message = ChannelRequest {
+            recipient_channel: 0,
+            request_type: ChannelRequestType::Exec {
+                command: "uptime".into(),
+            },
+            want_reply: true,
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(
+            encoded,
+            b"\x62\0\0\0\0\0\0\0\x04exec\x01\0\0\0\x06uptime".to_vec()
+        );
+        assert_eq!(ChannelRequest::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn channel_request_shell_round_trip() {
+        let message = ChannelRequest {
+            recipient_channel: 3,
+            request_type: ChannelRequestType::Shell,
+            want_reply: false,
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x62\0\0\0\x03\0\0\0\x05shell\0".to_vec());
+        assert_eq!(ChannelRequest::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn channel_request_other_types_round_trip() {
+        for request_type in [
+            ChannelRequestType::PtyReq {
+                term: "xterm".into(),
+                cols: 80,
+                rows: 24,
+                width_px: 640,
+                height_px: 480,
+                modes: vec![0],
+            },
+            ChannelRequestType::Subsystem {
+                name: "sftp".into(),
+            },
+            ChannelRequestType::Unknown {
+                name: "exit-status".into(),
+                data: vec![0, 0, 0, 1],
+            },
+        ] {
+            let message = ChannelRequest {
+                recipient_channel: 1,
+                request_type,
+                want_reply: false,
+            };
+
+            let encoded = message.encode().unwrap();
+
+            assert_eq!(ChannelRequest::decode(&encoded).unwrap(), message);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::prelude::*;
+
+/// Possible errors when decoding a message received over the wire
+#[derive(Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The data ended before the value being decoded was complete
+    ///
+    /// needed: the number of bytes required to decode the value
+    /// available: the number of bytes that were actually available
+    UnexpectedEnd { needed: usize, available: usize },
+    /// A name-list contained an empty name (from a leading, trailing or doubled comma)
+    ///
+    /// list: the full name-list as it was received
+    EmptyName { list: String },
+    /// A name-list contained characters that are not US-ASCII
+    ///
+    /// list: the full name-list as it was received, with invalid characters replaced
+    NonAsciiName { list: String },
+    /// The message type byte didn't match the message being decoded
+    UnexpectedMessageType { expected: u8, actual: u8 },
+    /// The message type byte isn't one this crate knows how to decode
+    UnknownMessageType { actual: u8 },
+    /// A string that must be UTF-8 contained invalid data
+    InvalidUtf8,
+    /// There was data left over after the message was decoded
+    ///
+    /// count: the number of unused bytes
+    TrailingBytes { count: usize },
+    /// A disconnect message contained a reason code that isn't defined by RFC 4253
+    UnknownDisconnectReason { code: u32 },
+    /// An mpint had unnecessary leading `0x00` or `0xff` bytes
+    NonMinimalMpint,
+    /// An mpint that must not be negative was
+    NegativeMpint,
+    /// A language name-list contained a tag that isn't valid according to RFC 3066
+    InvalidLanguageTag { tag: String },
+    /// A userauth request used an authentication method that isn't supported
+    UnknownUserauthMethod { name: String },
+}
+
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedEnd { needed, available } => write!(f, "Unexpected end of data: needed {needed} bytes but only {available} were available"),
+            Self::EmptyName { list } => write!(f, "Name-list must not contain empty names, got \"{list}\""),
+            Self::NonAsciiName { list } => write!(f, "Names in a name-list must only contain US-ASCII characters, got \"{list}\""),
+            Self::UnexpectedMessageType { expected, actual } => write!(f, "Expected message type {expected}, got {actual}"),
+            Self::UnknownMessageType { actual } => write!(f, "Unknown message type {actual}"),
+            Self::InvalidUtf8 => write!(f, "String was not valid UTF-8"),
+            Self::TrailingBytes { count } => write!(f, "Found {count} unexpected bytes after the end of the message"),
+            Self::UnknownDisconnectReason { code } => write!(f, "Unknown disconnect reason code {code}"),
+            Self::NonMinimalMpint => write!(f, "mpint must not have unnecessary leading bytes"),
+            Self::NegativeMpint => write!(f, "mpint must not be negative"),
+            Self::InvalidLanguageTag { tag } => write!(f, "Invalid language tag \"{tag}\""),
+            Self::UnknownUserauthMethod { name } => write!(f, "Unknown authentication method \"{name}\""),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
@@ -0,0 +1,117 @@
+//! The Diffie-Hellman key exchange messages from RFC 4253 section 8
+//!
+//! These use the same message numbers as the messages in `ecdh`, which one is meant depends on
+//! the negotiated key exchange method. Here the public values `e` and `f` are `mpint`s.
+
+use super::{message_type::MessageType, take_message_type, Message, MessageError};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// The `SSH_MSG_KEXDH_INIT` message, sent by the client with its public value
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KexDhInit {
+    /// The client's public value `e` as unsigned big endian bytes
+    pub e: Vec<u8>,
+}
+
+impl Message for KexDhInit {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_mpint(&self.e);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::KexEcdhInit
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::KexEcdhInit)?;
+        let e = reader.get_unsigned_mpint()?;
+        reader.expect_end()?;
+
+        Ok(Self { e })
+    }
+}
+
+/// The `SSH_MSG_KEXDH_REPLY` message, sent by the server with its host key, its public value and
+/// the signature over the exchange hash
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KexDhReply {
+    /// The server's public host key blob `K_S`
+    pub host_key: Vec<u8>,
+    /// The server's public value `f` as unsigned big endian bytes
+    pub f: Vec<u8>,
+    /// The signature of the exchange hash made with the host key
+    pub signature: Vec<u8>,
+}
+
+impl Message for KexDhReply {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(&self.host_key);
+        writer.put_mpint(&self.f);
+        writer.put_string(&self.signature);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::KexEcdhReply
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::KexEcdhReply)?;
+        let host_key = reader.get_string()?.to_vec();
+        let f = reader.get_unsigned_mpint()?;
+        let signature = reader.get_string()?.to_vec();
+        reader.expect_end()?;
+
+        Ok(Self {
+            host_key,
+            f,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::dh::*;
+    use crate::message::DecodeError;
+
+    #[test]
+    fn kex_dh_round_trip() {
+        let init = KexDhInit { e: vec![0x80, 1] };
+
+        let encoded = init.encode().unwrap();
+        assert_eq!(encoded, [30, 0, 0, 0, 3, 0, 0x80, 1]);
+        assert_eq!(KexDhInit::decode(&encoded), Ok(init));
+
+        let reply = KexDhReply {
+            host_key: b"host key".to_vec(),
+            f: vec![5; 256],
+            signature: b"signature".to_vec(),
+        };
+
+        let encoded = reply.encode().unwrap();
+        assert_eq!(KexDhReply::decode(&encoded), Ok(reply));
+    }
+
+    #[test]
+    fn kex_dh_rejects_negative_values() {
+        assert_eq!(
+            KexDhInit::decode(&[30, 0, 0, 0, 1, 0x80]),
+            Err(DecodeError::NegativeMpint.into())
+        );
+    }
+}
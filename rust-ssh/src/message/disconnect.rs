@@ -1,7 +1,15 @@
+use super::{message_type::MessageType, take_message_type, DecodeError, Message, MessageError};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// The reason codes for a disconnect message as defined by RFC 4253 section 11.1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum DisconnectMessageType {
     HostNotAllowedToConnect = 1,
     ProtocolError = 2,
     KeyExchangeFailed = 3,
+    /// Code 4 has no meaning in RFC 4253 but is still a valid value on the wire, so it is
+    /// decoded rather than rejected
     Reserved = 4,
     MacError = 5,
     CompressionError = 6,
@@ -16,10 +24,132 @@ pub enum DisconnectMessageType {
     IllegalUserName = 15,
 }
 
-impl std::fmt::Display for DisconnectMessageType {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            _ => write!(f, ""),
-        }
+impl core::fmt::Display for DisconnectMessageType {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::HostNotAllowedToConnect => "host not allowed to connect",
+                Self::ProtocolError => "protocol error",
+                Self::KeyExchangeFailed => "key exchange failed",
+                Self::Reserved => "(reserved)",
+                Self::MacError => "MAC error",
+                Self::CompressionError => "compression error",
+                Self::ServiceNotAvailable => "service not available",
+                Self::ProtocolVersionNotSupported => "protocol version not supported",
+                Self::HostKeyNotVerifiable => "host key not verifiable",
+                Self::ConnectionLost => "connection lost",
+                Self::ByApplication => "disconnected by application",
+                Self::TooManyConnections => "too many connections",
+                Self::AuthCancelledByUser => "auth cancelled by user",
+                Self::NoMoreAuthMethodsAvailable => "no more auth methods available",
+                Self::IllegalUserName => "illegal user name",
+            }
+        )
+    }
+}
+
+impl TryFrom<u32> for DisconnectMessageType {
+    type Error = DecodeError;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        Ok(match code {
+            1 => Self::HostNotAllowedToConnect,
+            2 => Self::ProtocolError,
+            3 => Self::KeyExchangeFailed,
+            4 => Self::Reserved,
+            5 => Self::MacError,
+            6 => Self::CompressionError,
+            7 => Self::ServiceNotAvailable,
+            8 => Self::ProtocolVersionNotSupported,
+            9 => Self::HostKeyNotVerifiable,
+            10 => Self::ConnectionLost,
+            11 => Self::ByApplication,
+            12 => Self::TooManyConnections,
+            13 => Self::AuthCancelledByUser,
+            14 => Self::NoMoreAuthMethodsAvailable,
+            15 => Self::IllegalUserName,
+            code => return Err(DecodeError::UnknownDisconnectReason { code }),
+        })
+    }
+}
+
+/// The `SSH_MSG_DISCONNECT` message, sent to terminate a connection
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisconnectMessage {
+    pub reason: DisconnectMessageType,
+    /// A human readable description of why the connection was closed
+    pub description: String,
+    /// The RFC 3066 language tag of the description, usually empty
+    pub language_tag: String,
+}
+
+impl Message for DisconnectMessage {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.reason as u32);
+        writer.put_string(self.description.as_bytes());
+        writer.put_string(self.language_tag.as_bytes());
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::Disconnect
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::Disconnect)?;
+        let reason = reader.get_u32()?;
+        let description = reader.get_utf8()?;
+        let language_tag = reader.get_utf8()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            reason: reason.try_into()?,
+            description,
+            language_tag,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::disconnect::*;
+
+    #[test]
+    fn disconnect_message_round_trip() {
+        let message = DisconnectMessage {
+            reason: DisconnectMessageType::ByApplication,
+            description: "goodbye".into(),
+            language_tag: "".into(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x01\0\0\0\x0b\0\0\0\x07goodbye\0\0\0\0".to_vec());
+        assert_eq!(DisconnectMessage::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn disconnect_message_reserved_reason() {
+        let encoded = b"\x01\0\0\0\x04\0\0\0\0\0\0\0\0".to_vec();
+
+        let message = DisconnectMessage::decode(&encoded).unwrap();
+        assert_eq!(message.reason, DisconnectMessageType::Reserved);
+        assert_eq!(message.reason.to_string(), "(reserved)");
+        assert_eq!(message.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn disconnect_message_unknown_reason() {
+        let encoded = b"\x01\0\0\0\x10\0\0\0\0\0\0\0\0".to_vec();
+
+        assert!(DisconnectMessage::decode(&encoded).is_err());
     }
 }
@@ -0,0 +1,141 @@
+//! The elliptic curve key exchange messages from RFC 5656 section 4
+//!
+//! Message numbers 30 to 49 are specific to the negotiated key exchange method, so the same
+//! numbers are used by other methods for different messages. For example 30 and 31 are also
+//! `SSH_MSG_KEXDH_INIT` and `SSH_MSG_KEXDH_REPLY` in the Diffie-Hellman methods of RFC 4253,
+//! where the public values are `mpint`s rather than strings. These messages should only be
+//! decoded while an ECDH style exchange such as `curve25519-sha256` is running.
+
+use super::{message_type::MessageType, take_message_type, Message, MessageError};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// The `SSH_MSG_KEX_ECDH_INIT` message, sent by the client with its ephemeral public key
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KexEcdhInit {
+    /// The client's ephemeral public key `Q_C`
+    pub client_public: Vec<u8>,
+}
+
+impl Message for KexEcdhInit {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(&self.client_public);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::KexEcdhInit
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::KexEcdhInit)?;
+        let client_public = reader.get_string()?.to_vec();
+        reader.expect_end()?;
+
+        Ok(Self { client_public })
+    }
+}
+
+/// The `SSH_MSG_KEX_ECDH_REPLY` message, sent by the server with its host key, its ephemeral
+/// public key and the signature over the exchange hash
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KexEcdhReply {
+    /// The server's public host key blob `K_S`
+    pub host_key: Vec<u8>,
+    /// The server's ephemeral public key `Q_S`
+    pub server_public: Vec<u8>,
+    /// The signature of the exchange hash made with the host key
+    pub signature: Vec<u8>,
+}
+
+impl Message for KexEcdhReply {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(&self.host_key);
+        writer.put_string(&self.server_public);
+        writer.put_string(&self.signature);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::KexEcdhReply
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::KexEcdhReply)?;
+        let host_key = reader.get_string()?.to_vec();
+        let server_public = reader.get_string()?.to_vec();
+        let signature = reader.get_string()?.to_vec();
+        reader.expect_end()?;
+
+        Ok(Self {
+            host_key,
+            server_public,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::ecdh::*;
+    use crate::message::DecodeError;
+
+    #[test]
+    fn kex_ecdh_init_round_trip() {
+        let message = KexEcdhInit {
+            client_public: vec![9; 32],
+        };
+
+        let encoded = message.encode().unwrap();
+        assert_eq!(encoded[..5], [30, 0, 0, 0, 32]);
+        assert_eq!(encoded.len(), 37);
+        assert_eq!(KexEcdhInit::decode(&encoded), Ok(message));
+
+        assert_eq!(
+            KexEcdhInit::decode(&[30, 0, 0, 0, 32, 9]),
+            Err(DecodeError::UnexpectedEnd {
+                needed: 36,
+                available: 5
+            }
+            .into())
+        );
+    }
+
+    #[test]
+    fn kex_ecdh_reply_round_trip() {
+        let message = KexEcdhReply {
+            host_key: b"host key".to_vec(),
+            server_public: vec![5; 32],
+            signature: b"signature".to_vec(),
+        };
+
+        let encoded = message.encode().unwrap();
+        assert_eq!(encoded[..5], [31, 0, 0, 0, 8]);
+        assert_eq!(KexEcdhReply::decode(&encoded), Ok(message));
+
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(KexEcdhReply::decode(&trailing).is_err());
+
+        assert_eq!(
+            KexEcdhReply::decode(&[30, 0, 0, 0, 0]),
+            Err(DecodeError::UnexpectedMessageType {
+                expected: 31,
+                actual: 30
+            }
+            .into())
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use crate::prelude::*;
+
+/// Possible errors when encoding a message to be sent over the wire
+#[derive(Debug, Eq, PartialEq)]
+pub enum EncodeError {
+    /// A name in a name-list contained characters that are not US-ASCII
+    ///
+    /// name: the offending name
+    NonAsciiName { name: String },
+    /// A name in a name-list contained a comma, which is the separator between names
+    ///
+    /// name: the offending name
+    CommaInName { name: String },
+    /// A reserved field that must be sent as 0 had another value
+    NonZeroReserved { value: u32 },
+}
+
+impl core::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::NonAsciiName { name } => write!(f, "Names in a name-list must only contain US-ASCII characters, got \"{name}\""),
+            Self::CommaInName { name } => write!(f, "Names in a name-list must not contain commas, got \"{name}\""),
+            Self::NonZeroReserved { value } => write!(f, "Reserved field must be 0, got {value}"),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
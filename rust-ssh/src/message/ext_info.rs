@@ -0,0 +1,150 @@
+use super::{
+    message_type::MessageType, take_message_type, HostKeyAlgorithm, Message, MessageError, NameList,
+};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// One extension sent in an `ExtInfo` message
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Extension {
+    /// `server-sig-algs`, the public key signature algorithms the server accepts for user
+    /// authentication
+    ServerSigAlgs(Vec<HostKeyAlgorithm>),
+    /// An extension this crate doesn't know about, with its value still encoded
+    Other { name: String, value: Vec<u8> },
+}
+
+/// The `SSH_MSG_EXT_INFO` message from RFC 8308, which lists extensions supported by the sender
+///
+/// It can only be sent by a side whose peer included `ext-info-c` or `ext-info-s` in its first
+/// `KexInitMessage`, usually straight after the first `NewKeys`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExtInfo {
+    pub extensions: Vec<Extension>,
+}
+
+impl ExtInfo {
+    /// The signature algorithms from the `server-sig-algs` extension, if it was sent
+    pub fn server_sig_algs(&self) -> Option<&[HostKeyAlgorithm]> {
+        self.extensions
+            .iter()
+            .find_map(|extension| match extension {
+                Extension::ServerSigAlgs(algorithms) => Some(algorithms.as_slice()),
+                Extension::Other { .. } => None,
+            })
+    }
+}
+
+impl Message for ExtInfo {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_u32(self.extensions.len() as u32);
+        for extension in &self.extensions {
+            match extension {
+                Extension::ServerSigAlgs(algorithms) => {
+                    writer.put_string(b"server-sig-algs");
+                    writer.put_name_list(&NameList::from(algorithms.clone()))?;
+                }
+                Extension::Other { name, value } => {
+                    writer.put_string(name.as_bytes());
+                    writer.put_string(value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::ExtInfo
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::ExtInfo)?;
+
+        let count = reader.get_u32()?;
+        let mut extensions = vec![];
+        for _ in 0..count {
+            let name = reader.get_utf8()?;
+            extensions.push(match name.as_str() {
+                // The value is itself a name-list, so it is decoded as one
+                "server-sig-algs" => {
+                    Extension::ServerSigAlgs(reader.get_name_list()?.parse().into_iter().collect())
+                }
+                _ => Extension::Other {
+                    name,
+                    value: reader.get_string()?.to_vec(),
+                },
+            });
+        }
+        reader.expect_end()?;
+
+        Ok(Self { extensions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::ext_info::*;
+    use crate::message::DecodeError;
+
+    #[test]
+    fn ext_info_round_trip() {
+        let message = ExtInfo {
+            extensions: vec![
+                Extension::ServerSigAlgs(vec![
+                    HostKeyAlgorithm::SshEd25519,
+                    HostKeyAlgorithm::RsaSha2_512,
+                ]),
+                Extension::Other {
+                    name: "no-flow-control".into(),
+                    value: b"p".to_vec(),
+                },
+            ],
+        };
+
+        let encoded = message.encode().unwrap();
+        assert_eq!(encoded[..5], [7, 0, 0, 0, 2]);
+        assert_eq!(ExtInfo::decode(&encoded), Ok(message.clone()));
+
+        assert_eq!(
+            message.server_sig_algs(),
+            Some(&[HostKeyAlgorithm::SshEd25519, HostKeyAlgorithm::RsaSha2_512][..])
+        );
+        assert_eq!(ExtInfo { extensions: vec![] }.server_sig_algs(), None);
+    }
+
+    #[test]
+    fn ext_info_decodes_openssh_server_sig_algs() {
+        let mut data = vec![7, 0, 0, 0, 1];
+        let mut writer = Writer::append_to(&mut data);
+        writer.put_string(b"server-sig-algs");
+        writer.put_string(b"ssh-ed25519,rsa-sha2-256,sk-ssh-ed25519@openssh.com");
+
+        let message = ExtInfo::decode(&data).unwrap();
+        assert_eq!(
+            message.server_sig_algs(),
+            Some(
+                &[
+                    HostKeyAlgorithm::SshEd25519,
+                    HostKeyAlgorithm::RsaSha2_256,
+                    HostKeyAlgorithm::Unknown("sk-ssh-ed25519@openssh.com".into()),
+                ][..]
+            )
+        );
+
+        // There are fewer extensions than the count says
+        assert_eq!(
+            ExtInfo::decode(&[7, 0, 0, 0, 1]),
+            Err(DecodeError::UnexpectedEnd {
+                needed: 4,
+                available: 0
+            }
+            .into())
+        );
+    }
+}
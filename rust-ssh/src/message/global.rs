@@ -0,0 +1,154 @@
+use super::{message_type::MessageType, take_message_type, Message, MessageError};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// The `SSH_MSG_GLOBAL_REQUEST` message, for requests that aren't specific to a channel as
+/// defined by RFC 4254 section 4
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GlobalRequest {
+    /// The kind of request, such as `tcpip-forward`
+    pub request_name: String,
+    /// Whether a `RequestSuccess` or `RequestFailure` response should be sent
+    pub want_reply: bool,
+    /// Any additional fields specific to the request, already encoded
+    pub data: Vec<u8>,
+}
+
+impl Message for GlobalRequest {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(self.request_name.as_bytes());
+        writer.put_bool(self.want_reply);
+        writer.put_bytes(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::GlobalRequest
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::GlobalRequest)?;
+
+        Ok(Self {
+            request_name: reader.get_utf8()?,
+            want_reply: reader.get_bool()?,
+            data: reader.remaining().to_vec(),
+        })
+    }
+}
+
+/// The `SSH_MSG_REQUEST_SUCCESS` message, sent in reply to a `GlobalRequest` that succeeded
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestSuccess {
+    /// Any response fields specific to the request, already encoded
+    pub data: Vec<u8>,
+}
+
+impl Message for RequestSuccess {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_bytes(&self.data);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::RequestSuccess
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::RequestSuccess)?;
+
+        Ok(Self {
+            data: reader.remaining().to_vec(),
+        })
+    }
+}
+
+/// The `SSH_MSG_REQUEST_FAILURE` message, sent in reply to a `GlobalRequest` that failed or
+/// isn't supported
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestFailure;
+
+impl Message for RequestFailure {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::RequestFailure
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::RequestFailure)?;
+        reader.expect_end()?;
+
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::global::*;
+
+    #[test]
+    fn global_request_round_trip() {
+        // tcpip-forward with the address and port to bind
+        let message = GlobalRequest {
+            request_name: "tcpip-forward".into(),
+            want_reply: true,
+            data: b"\0\0\0\x09localhost\0\0\x1f\x90".to_vec(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(
+            encoded,
+            b"\x50\0\0\0\x0dtcpip-forward\x01\0\0\0\x09localhost\0\0\x1f\x90".to_vec()
+        );
+        assert_eq!(GlobalRequest::decode(&encoded).unwrap(), message);
+
+        let message = GlobalRequest {
+            request_name: "keepalive@openssh.com".into(),
+            want_reply: false,
+            data: vec![],
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded[encoded.len() - 1], 0);
+        assert_eq!(GlobalRequest::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn request_replies_round_trip() {
+        // The port the server bound when 0 was requested
+        let success = RequestSuccess {
+            data: vec![0, 0, 0x1f, 0x90],
+        };
+        let encoded = success.encode().unwrap();
+
+        assert_eq!(encoded, [81, 0, 0, 0x1f, 0x90]);
+        assert_eq!(RequestSuccess::decode(&encoded).unwrap(), success);
+
+        assert_eq!(RequestFailure.encode().unwrap(), [82]);
+        assert_eq!(RequestFailure::decode(&[82]).unwrap(), RequestFailure);
+        assert!(RequestFailure::decode(&[82, 0]).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use super::{DecodeError, EncodeError};
+
+/// Possible errors when encoding or decoding a message
+#[derive(Debug, Eq, PartialEq)]
+pub enum MessageError {
+    /// The message couldn't be encoded
+    Encode(EncodeError),
+    /// The received data wasn't a valid message
+    Decode(DecodeError),
+}
+
+impl core::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Encode(e) => write!(f, "Failed to encode message: {e}"),
+            Self::Decode(e) => write!(f, "Failed to decode message: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for MessageError {}
+
+impl From<EncodeError> for MessageError {
+    fn from(e: EncodeError) -> Self {
+        Self::Encode(e)
+    }
+}
+
+impl From<DecodeError> for MessageError {
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
@@ -1,4 +1,5 @@
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum MessageType {
     Disconnect = 1,
     Ignore = 2,
@@ -7,15 +8,21 @@ pub enum MessageType {
 
     ServiceRequest = 5,
     ServiceAccept = 6,
+    ExtInfo = 7,
 
     KexInit = 20,
     NewKeys = 21,
 
+    KexEcdhInit = 30,
+    KexEcdhReply = 31,
+
     UserauthRequest = 50,
     UserauthFailure = 51,
     UserauthSuccess = 52,
     UserauthBanner = 53,
 
+    UserauthPasswdChangeReq = 60,
+
     GlobalRequest = 80,
     RequestSuccess = 81,
     RequestFailure = 82,
@@ -32,3 +39,44 @@ pub enum MessageType {
     ChannelSuccess = 99,
     ChannelFailure = 100,
 }
+
+impl TryFrom<u8> for MessageType {
+    type Error = super::DecodeError;
+
+    /// Look up a message type from its number, which must be one this crate knows about
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            1 => Self::Disconnect,
+            2 => Self::Ignore,
+            3 => Self::Unimplemented,
+            4 => Self::Debug,
+            5 => Self::ServiceRequest,
+            6 => Self::ServiceAccept,
+            7 => Self::ExtInfo,
+            20 => Self::KexInit,
+            21 => Self::NewKeys,
+            30 => Self::KexEcdhInit,
+            31 => Self::KexEcdhReply,
+            50 => Self::UserauthRequest,
+            51 => Self::UserauthFailure,
+            52 => Self::UserauthSuccess,
+            53 => Self::UserauthBanner,
+            60 => Self::UserauthPasswdChangeReq,
+            80 => Self::GlobalRequest,
+            81 => Self::RequestSuccess,
+            82 => Self::RequestFailure,
+            90 => Self::ChannelOpen,
+            91 => Self::ChannelOpenConfirmation,
+            92 => Self::ChannelOpenFailure,
+            93 => Self::ChannelWindowAdjust,
+            94 => Self::ChannelData,
+            95 => Self::ChannelExtendedData,
+            96 => Self::ChannelEOF,
+            97 => Self::ChannelClose,
+            98 => Self::ChannelRequest,
+            99 => Self::ChannelSuccess,
+            100 => Self::ChannelFailure,
+            actual => return Err(super::DecodeError::UnknownMessageType { actual }),
+        })
+    }
+}
@@ -1,50 +1,208 @@
+mod decode_error;
+pub mod additional;
+pub mod channel;
+pub mod dh;
 pub mod disconnect;
+pub mod ecdh;
+pub mod ext_info;
+mod encode_error;
+pub mod global;
+mod message_error;
 pub mod message_type;
+pub mod userauth;
 
-use result::Result;
+pub use decode_error::*;
+pub use encode_error::*;
+pub use message_error::*;
 
+use crate::codec::{Reader, Writer};
+use crate::encryption::CipherType;
+use crate::mac::Mac;
+use crate::prelude::*;
 use message_type::*;
 
+use rand::{CryptoRng, RngCore};
+
 pub trait Message {
-    fn encode(&self) -> Vec<u8>;
+    /// Append the message to `buffer` as a packet payload, starting with the message type byte
+    ///
+    /// This lets one buffer be reused for many messages. If an error is returned part of the
+    /// message may already have been appended.
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError>;
+    /// Encode the message as a packet payload in a new buffer, see `encode_into`
+    fn encode(&self) -> Result<Vec<u8>, MessageError> {
+        let mut buffer = vec![];
+        self.encode_into(&mut buffer)?;
+
+        Ok(buffer)
+    }
     fn get_type(&self) -> MessageType;
-    fn decode(data: Vec<u8>) -> Result<Self> where Self: Sized + Message;
+    /// Decode a message from a whole packet payload, including the message type byte
+    ///
+    /// The type byte is checked against the message being decoded and any data left over after
+    /// the message is an error, so `decode` accepts exactly what `encode` produces.
+    fn decode(data: &[u8]) -> Result<Self, MessageError> where Self: Sized + Message;
+}
+
+/// Decode any message this crate knows about from a whole packet payload
+///
+/// The message type byte picks which message is decoded, types without a message struct give
+/// `DecodeError::UnknownMessageType`. Like every `decode`, this returns an error rather than
+/// panicking however malformed `data` is.
+pub fn decode_any_message(data: &[u8]) -> Result<Box<dyn Message>, MessageError> {
+    fn boxed<M: Message + 'static>(data: &[u8]) -> Result<Box<dyn Message>, MessageError> {
+        Ok(Box::new(M::decode(data)?))
+    }
+
+    let message_type = match data.first() {
+        Some(&value) => MessageType::try_from(value)?,
+        None => {
+            return Err(DecodeError::UnexpectedEnd {
+                needed: 1,
+                available: 0,
+            }
+            .into())
+        }
+    };
+
+    match message_type {
+        MessageType::Disconnect => boxed::<disconnect::DisconnectMessage>(data),
+        MessageType::Ignore => boxed::<additional::IgnoreMessage>(data),
+        MessageType::Unimplemented => boxed::<additional::UnimplementedMessage>(data),
+        MessageType::Debug => boxed::<additional::DebugMessage>(data),
+        MessageType::ServiceRequest => boxed::<ServiceRequest>(data),
+        MessageType::ExtInfo => boxed::<ext_info::ExtInfo>(data),
+        MessageType::KexInit => boxed::<KexInitMessage>(data),
+        MessageType::NewKeys => boxed::<NewKeys>(data),
+        MessageType::KexEcdhInit => boxed::<ecdh::KexEcdhInit>(data),
+        MessageType::KexEcdhReply => boxed::<ecdh::KexEcdhReply>(data),
+        MessageType::UserauthRequest => boxed::<userauth::UserauthRequest>(data),
+        MessageType::UserauthFailure => boxed::<userauth::UserauthFailure>(data),
+        MessageType::UserauthSuccess => boxed::<userauth::UserauthSuccess>(data),
+        MessageType::UserauthBanner => boxed::<userauth::UserauthBanner>(data),
+        MessageType::UserauthPasswdChangeReq => boxed::<userauth::PasswdChangeReq>(data),
+        MessageType::GlobalRequest => boxed::<global::GlobalRequest>(data),
+        MessageType::RequestSuccess => boxed::<global::RequestSuccess>(data),
+        MessageType::RequestFailure => boxed::<global::RequestFailure>(data),
+        MessageType::ChannelOpen => boxed::<channel::ChannelOpen>(data),
+        MessageType::ChannelOpenConfirmation => boxed::<channel::ChannelOpenConfirmation>(data),
+        MessageType::ChannelWindowAdjust => boxed::<channel::ChannelWindowAdjust>(data),
+        MessageType::ChannelData => boxed::<channel::ChannelData>(data),
+        MessageType::ChannelExtendedData => boxed::<channel::ChannelExtendedData>(data),
+        MessageType::ChannelEOF => boxed::<channel::ChannelEof>(data),
+        MessageType::ChannelClose => boxed::<channel::ChannelClose>(data),
+        MessageType::ChannelRequest => boxed::<channel::ChannelRequest>(data),
+        message_type => Err(DecodeError::UnknownMessageType {
+            actual: message_type as u8,
+        }
+        .into()),
+    }
 }
 
-pub struct NameList<T: std::fmt::Display> {
+/// Check that the next byte read is the type byte of the message being decoded
+fn take_message_type(
+    reader: &mut Reader,
+    expected: MessageType,
+) -> core::result::Result<(), DecodeError> {
+    let expected = expected as u8;
+
+    match reader.get_u8() {
+        Ok(actual) if actual == expected => Ok(()),
+        Ok(actual) => Err(DecodeError::UnexpectedMessageType { expected, actual }),
+        Err(_) => Err(DecodeError::UnexpectedEnd {
+            needed: 1,
+            available: 0,
+        }),
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NameList<T: core::fmt::Display> {
     this: Vec<T>,
 }
 
-impl<T: std::fmt::Display> NameList<T> {
-    pub fn encode(&self) -> Vec<u8> {
-        let mut encoded = vec![];
-        let str_nl = format!("{}", self);
+impl<T: core::fmt::Display> NameList<T> {
+    /// Encode the name-list as a length prefixed string of comma separated names
+    ///
+    /// Names are required to be US-ASCII by RFC 4251, so any name that isn't is rejected rather
+    /// than being silently truncated to bytes. Names containing a comma are rejected as well.
+    pub fn encode(&self) -> core::result::Result<Vec<u8>, EncodeError> {
+        let mut writer = Writer::new();
+        writer.put_name_list(self)?;
 
-        for byte in (str_nl.len() as u32).to_be_bytes() {
-            encoded.push(byte);
-        }
+        Ok(writer.into_bytes())
+    }
 
-        for byte in str_nl.chars().map(|x| x as u8) {
-            encoded.push(byte);
-        }
+    /// The number of names in the list
+    pub fn len(&self) -> usize {
+        self.this.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.this.is_empty()
+    }
+
+    /// Iterate over the names in order of preference
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.this.iter()
+    }
+}
+
+impl<T: core::fmt::Display> IntoIterator for NameList<T> {
+    type Item = T;
+    type IntoIter = alloc::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.this.into_iter()
+    }
+}
+
+impl<'a, T: core::fmt::Display> IntoIterator for &'a NameList<T> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.this.iter()
+    }
+}
+
+impl NameList<String> {
+    /// Decode a length prefixed name-list from the start of `data`
+    ///
+    /// Returns the decoded list along with the number of bytes consumed from `data`. An empty
+    /// string decodes to an empty list, but empty names inside of a list are rejected.
+    pub fn decode(data: &[u8]) -> core::result::Result<(NameList<String>, usize), DecodeError> {
+        let mut reader = Reader::new(data);
+        let list = reader.get_name_list()?;
+
+        Ok((list, reader.position()))
+    }
 
-        encoded
+    /// Convert each name into an algorithm, names that aren't recognized are kept as unknown
+    fn parse<T>(self) -> NameList<T>
+    where
+        T: core::fmt::Display + core::str::FromStr<Err = core::convert::Infallible>,
+    {
+        NameList {
+            this: self.iter().map(|name| name.parse().unwrap()).collect(),
+        }
     }
 }
 
-impl<T: std::fmt::Display> std::fmt::Display for NameList<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let inter: String = ",".to_string();
-        let str_list = self.this.iter().map(|x| format!("{x}")).intersperse(inter);
-        for item in str_list {
-            write!(f, "{}", item)?
+impl<T: core::fmt::Display> core::fmt::Display for NameList<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (i, item) in self.this.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{item}")?;
         }
 
         Ok(())
     }
 }
 
-impl<T: std::fmt::Display> From<Vec<T>> for NameList<T> {
+impl<T: core::fmt::Display> From<Vec<T>> for NameList<T> {
     fn from(vec: Vec<T>) -> Self {
         Self {
             this: vec
@@ -52,14 +210,48 @@ impl<T: std::fmt::Display> From<Vec<T>> for NameList<T> {
     }
 }
 
+/// Pick the first algorithm the client prefers that the server also supports
+///
+/// This is the rule RFC 4253 section 7.1 uses for every algorithm negotiated in a
+/// `KexInitMessage`, returning `None` when there is no algorithm in common.
+pub fn negotiate<T>(client: &NameList<T>, server: &NameList<T>) -> Option<T>
+where
+    T: core::fmt::Display + PartialEq + Clone,
+{
+    client.iter().find(|x| server.iter().any(|y| y == *x)).cloned()
+}
+
+/// Pick the key exchange method like `negotiate`, ignoring `ext-info-c` and `ext-info-s` as they
+/// only signal support for extensions and aren't real methods
+pub fn negotiate_kex(
+    client: &NameList<KexAlgorithm>,
+    server: &NameList<KexAlgorithm>,
+) -> Option<KexAlgorithm> {
+    client
+        .iter()
+        .filter(|x| !x.is_extension_signal())
+        .find(|x| server.iter().any(|y| y == *x))
+        .cloned()
+}
+
+/// Pick the first name the client prefers that the server also supports, from two raw comma
+/// separated name-lists
+pub fn negotiate_names<'a>(client: &'a str, server: &str) -> Option<&'a str> {
+    client
+        .split(',')
+        .filter(|x| !x.is_empty())
+        .find(|x| server.split(',').any(|y| y == *x))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum SSHService {
     UserAuth,
     Connection,
     Named(String),
 }
 
-impl std::fmt::Display for SSHService {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for SSHService {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "{}", match self {
             Self::UserAuth => "ssh-userauth",
             Self::Connection => "ssh-connection",
@@ -68,6 +260,18 @@ impl std::fmt::Display for SSHService {
     }
 }
 
+impl core::str::FromStr for SSHService {
+    type Err = core::convert::Infallible;
+
+    fn from_str(name: &str) -> core::result::Result<Self, Self::Err> {
+        Ok(match name {
+            "ssh-userauth" => Self::UserAuth,
+            "ssh-connection" => Self::Connection,
+            name => Self::Named(name.into()),
+        })
+    }
+}
+
 pub struct ServiceRequest {
     pub service: SSHService
 }
@@ -77,76 +281,283 @@ impl Message for ServiceRequest {
         MessageType::ServiceRequest
     }
 
-    fn decode(_data: Vec<u8>) -> Result<Self> {
-        todo!()
-    }
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
 
-    fn encode(&self) -> Vec<u8> {
-        let mut encoded = vec![];
+        take_message_type(&mut reader, MessageType::ServiceRequest)?;
+        let service = reader.get_utf8()?;
+        reader.expect_end()?;
 
-        encoded.push(self.get_type() as u8);
+        Ok(Self {
+            service: service.parse().unwrap(),
+        })
+    }
 
-        let service = format!("{}", self.service);
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
 
-        for byte in (service.len() as u32).to_be_bytes() {
-            encoded.push(byte);
-        }
-        encoded.append(&mut service.chars().map(|x| x as u8).collect());
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(self.service.to_string().as_bytes());
 
-        encoded
+        Ok(())
     }
 }
 
-use std::fmt::{Display, Formatter};
-pub enum KexAlgorithm {}
+use core::fmt::{Display, Formatter};
+/// Key exchange methods that can be offered in a `KexInitMessage`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KexAlgorithm {
+    Curve25519Sha256,
+    Curve25519Sha256Libssh,
+    EcdhSha2Nistp256,
+    DiffieHellmanGroup16Sha512,
+    DiffieHellmanGroup14Sha256,
+    /// Not a key exchange method, sent by a client to say that it accepts `SSH_MSG_EXT_INFO`
+    ExtInfoC,
+    /// Not a key exchange method, sent by a server to say that it accepts `SSH_MSG_EXT_INFO`
+    ExtInfoS,
+    /// An algorithm this crate doesn't know about, kept so that it can be passed on unchanged
+    Unknown(String),
+}
+
+impl KexAlgorithm {
+    /// Whether this is one of the RFC 8308 `ext-info-*` names, which must never be negotiated
+    pub fn is_extension_signal(&self) -> bool {
+        matches!(self, Self::ExtInfoC | Self::ExtInfoS)
+    }
+}
 
 impl Display for KexAlgorithm {
-    fn fmt(&self, _f: &mut Formatter) -> std::fmt::Result {
-        Ok(())
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Curve25519Sha256 => "curve25519-sha256",
+            Self::Curve25519Sha256Libssh => "curve25519-sha256@libssh.org",
+            Self::EcdhSha2Nistp256 => "ecdh-sha2-nistp256",
+            Self::DiffieHellmanGroup16Sha512 => "diffie-hellman-group16-sha512",
+            Self::DiffieHellmanGroup14Sha256 => "diffie-hellman-group14-sha256",
+            Self::ExtInfoC => "ext-info-c",
+            Self::ExtInfoS => "ext-info-s",
+            Self::Unknown(name) => name,
+        })
     }
 }
 
-pub enum EncryptionAlgorithm {}
+impl core::str::FromStr for KexAlgorithm {
+    type Err = core::convert::Infallible;
 
-impl Display for EncryptionAlgorithm {
-    fn fmt(&self, _f: &mut Formatter) -> std::fmt::Result {
-        Ok(())
+    fn from_str(name: &str) -> core::result::Result<Self, Self::Err> {
+        Ok(match name {
+            "curve25519-sha256" => Self::Curve25519Sha256,
+            "curve25519-sha256@libssh.org" => Self::Curve25519Sha256Libssh,
+            "ecdh-sha2-nistp256" => Self::EcdhSha2Nistp256,
+            "diffie-hellman-group16-sha512" => Self::DiffieHellmanGroup16Sha512,
+            "diffie-hellman-group14-sha256" => Self::DiffieHellmanGroup14Sha256,
+            "ext-info-c" => Self::ExtInfoC,
+            "ext-info-s" => Self::ExtInfoS,
+            name => Self::Unknown(name.into()),
+        })
     }
 }
 
-pub enum MacAlgorithm {}
-impl Display for MacAlgorithm{
-    fn fmt(&self, _f: &mut Formatter) -> std::fmt::Result {
-        Ok(())
+/// Server host key (and signature) algorithms that can be offered in a `KexInitMessage`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HostKeyAlgorithm {
+    SshEd25519,
+    EcdsaSha2Nistp256,
+    RsaSha2_512,
+    RsaSha2_256,
+    SshRsa,
+    /// An algorithm this crate doesn't know about, kept so that it can be passed on unchanged
+    Unknown(String),
+}
+
+impl Display for HostKeyAlgorithm {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", match self {
+            Self::SshEd25519 => "ssh-ed25519",
+            Self::EcdsaSha2Nistp256 => "ecdsa-sha2-nistp256",
+            Self::RsaSha2_512 => "rsa-sha2-512",
+            Self::RsaSha2_256 => "rsa-sha2-256",
+            Self::SshRsa => "ssh-rsa",
+            Self::Unknown(name) => name,
+        })
     }
 }
 
-pub enum Language {}
-impl Display for Language{
-    fn fmt(&self, _f: &mut Formatter) -> std::fmt::Result {
-        Ok(())
+impl core::str::FromStr for HostKeyAlgorithm {
+    type Err = core::convert::Infallible;
+
+    fn from_str(name: &str) -> core::result::Result<Self, Self::Err> {
+        Ok(match name {
+            "ssh-ed25519" => Self::SshEd25519,
+            "ecdsa-sha2-nistp256" => Self::EcdsaSha2Nistp256,
+            "rsa-sha2-512" => Self::RsaSha2_512,
+            "rsa-sha2-256" => Self::RsaSha2_256,
+            "ssh-rsa" => Self::SshRsa,
+            name => Self::Unknown(name.into()),
+        })
     }
 }
 
+/// Symmetric encryption algorithms that can be offered in a `KexInitMessage`
+///
+/// The negotiated algorithm can be instantiated directly, so this is now just another name for
+/// `CipherType`.
+#[deprecated(note = "use `encryption::CipherType` instead")]
+pub type EncryptionAlgorithm = CipherType;
+
+/// Message authentication code algorithms that can be offered in a `KexInitMessage`
+///
+/// The negotiated algorithm can be used directly, so this is now just another name for
+/// `mac::Mac`.
+#[deprecated(note = "use `mac::Mac` instead")]
+pub type MacAlgorithm = Mac;
+
+/// A language tag as defined by RFC 3066, e.g. `en-US`
+///
+/// The language name-lists of a `KexInitMessage` are almost always empty, which is encoded as a
+/// zero-length string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Language {
+    /// A tag that was checked by `Language::new`
+    Tag(String),
+    /// A tag received from a peer that isn't valid, kept like an unknown algorithm since
+    /// languages are never negotiated and one odd tag shouldn't stop the key exchange
+    Unknown(String),
+}
+
+impl Language {
+    /// Check that `tag` is a valid language tag
+    ///
+    /// A tag is a primary subtag of 1 to 8 letters followed by any number of subtags of 1 to 8
+    /// letters or digits, all separated by hyphens.
+    pub fn new(tag: &str) -> core::result::Result<Self, DecodeError> {
+        let mut subtags = tag.split('-');
+        let valid_subtag = |subtag: &str, is_valid: fn(&u8) -> bool| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| is_valid(&b))
+        };
+
+        let valid = subtags
+            .next()
+            .is_some_and(|primary| valid_subtag(primary, u8::is_ascii_alphabetic))
+            && subtags.all(|subtag| valid_subtag(subtag, u8::is_ascii_alphanumeric));
+        if !valid {
+            return Err(DecodeError::InvalidLanguageTag { tag: tag.into() });
+        }
+
+        Ok(Self::Tag(tag.into()))
+    }
+
+    /// The tag as it is sent in a name-list
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Tag(tag) | Self::Unknown(tag) => tag,
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl core::str::FromStr for Language {
+    type Err = DecodeError;
+
+    fn from_str(tag: &str) -> core::result::Result<Self, Self::Err> {
+        Self::new(tag)
+    }
+}
+
+/// Check every tag in a language name-list, keeping invalid ones as `Language::Unknown`
+fn languages(list: NameList<String>) -> NameList<Language> {
+    list.into_iter()
+        .map(|tag| Language::new(&tag).unwrap_or(Language::Unknown(tag)))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Compression algorithms that can be offered in a `KexInitMessage`
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum CompressionAlgorithm {
     Zstd,
-    None
+    None,
+    /// An algorithm this crate doesn't know about, kept so that it can be passed on unchanged
+    Unknown(String),
 }
-impl Display for CompressionAlgorithm{
-    fn fmt(&self, _f: &mut Formatter) -> std::fmt::Result {
-        Ok(())
+
+impl Display for CompressionAlgorithm {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Zstd => "zstd@openssh.com",
+            Self::None => "none",
+            Self::Unknown(name) => name,
+        })
     }
 }
+
+impl core::str::FromStr for CompressionAlgorithm {
+    type Err = core::convert::Infallible;
+
+    fn from_str(name: &str) -> core::result::Result<Self, Self::Err> {
+        Ok(match name {
+            "zstd@openssh.com" => Self::Zstd,
+            "none" => Self::None,
+            name => Self::Unknown(name.into()),
+        })
+    }
+}
+
+/// The 16 random bytes at the start of a `KexInitMessage`, which stop either side from fully
+/// controlling the exchange hash
+///
+/// Use `Cookie::random`, a fixed cookie such as all zeroes is insecure.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cookie([u8; 16]);
+
+impl Cookie {
+    /// Generate a new cookie using the operating system's random number generator
+    #[cfg(feature = "std")]
+    pub fn random() -> Self {
+        Self::random_with_rng(&mut rand::rngs::OsRng)
+    }
+
+    /// Generate a new cookie from `rng`
+    pub fn random_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut cookie = [0; 16];
+        rng.fill_bytes(&mut cookie);
+
+        Self(cookie)
+    }
+
+    /// Use the given bytes as the cookie, such as one received from the other side
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for Cookie {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct KexInitMessage {
-    pub cookie: [u8; 16],
+    pub cookie: Cookie,
     pub kex_algorithms: NameList<KexAlgorithm>,
-    pub server_host_key_algorithms: NameList<KexAlgorithm>,
+    pub server_host_key_algorithms: NameList<HostKeyAlgorithm>,
 
-    pub encryption_algorithms_client_to_server: NameList<EncryptionAlgorithm>,
-    pub encryption_algorithms_server_to_client: NameList<EncryptionAlgorithm>,
+    pub encryption_algorithms_client_to_server: NameList<CipherType>,
+    pub encryption_algorithms_server_to_client: NameList<CipherType>,
 
-    pub mac_algorithms_client_to_server: NameList<MacAlgorithm>,
-    pub mac_algorithms_server_to_client: NameList<MacAlgorithm>,
+    pub mac_algorithms_client_to_server: NameList<Mac>,
+    pub mac_algorithms_server_to_client: NameList<Mac>,
 
     pub compression_algorithms_client_to_server: NameList<CompressionAlgorithm>,
     pub compression_algorithms_server_to_client: NameList<CompressionAlgorithm>,
@@ -156,58 +567,822 @@ pub struct KexInitMessage {
 
     pub first_kex_packet_follows: bool,
 
-    #[allow(unused)]
+    /// Reserved for future extension, this must be 0 when sending but is kept as received
     pub reserved: u32,
 }
 
-impl Message for KexInitMessage {
-    fn encode(&self) -> Vec<u8> {
-        let mut encoded = vec![];
+impl KexInitMessage {
+    /// A proposal offering every algorithm this crate implements, in order of preference
+    ///
+    /// The cookie is generated with the operating system's random number generator. `ext-info-c`
+    /// is added after the real key exchange methods, it should only be sent in the first key
+    /// exchange of a connection.
+    #[cfg(feature = "std")]
+    pub fn default_client() -> Self {
+        Self::with_default_algorithms(Cookie::random()).with_kex_extension(KexAlgorithm::ExtInfoC)
+    }
+
+    /// A server proposal offering every algorithm this crate implements, see `default_client`
+    ///
+    /// The server needs a host key for each of `server_host_key_algorithms`, remove any it
+    /// doesn't have.
+    #[cfg(feature = "std")]
+    pub fn default_server() -> Self {
+        Self::with_default_algorithms(Cookie::random()).with_kex_extension(KexAlgorithm::ExtInfoS)
+    }
+
+    /// Add an extension signal such as `ext-info-c` to the end of `kex_algorithms`, where it
+    /// can't be picked as the key exchange method by a peer that doesn't know about it
+    pub fn with_kex_extension(mut self, signal: KexAlgorithm) -> Self {
+        self.kex_algorithms = self
+            .kex_algorithms
+            .into_iter()
+            .chain(core::iter::once(signal))
+            .collect::<Vec<_>>()
+            .into();
+
+        self
+    }
 
-        encoded.push(self.get_type() as u8);
+    /// Whether a key exchange packet sent because of `first_kex_packet_follows` used the right
+    /// algorithms
+    ///
+    /// RFC 4253 section 7 says the guess is right when both sides prefer the same key exchange
+    /// method and the same host key algorithm. When it is wrong the receiver must ignore the
+    /// packet that followed the `KexInitMessage`.
+    pub fn guess_matches(&self, other: &KexInitMessage) -> bool {
+        let first_kex = (
+            self.kex_algorithms.iter().next(),
+            other.kex_algorithms.iter().next(),
+        );
+        let first_host_key = (
+            self.server_host_key_algorithms.iter().next(),
+            other.server_host_key_algorithms.iter().next(),
+        );
 
-        for elem in self.cookie {
-            encoded.push(elem);
+        matches!(first_kex, (Some(a), Some(b)) if a == b)
+            && matches!(first_host_key, (Some(a), Some(b)) if a == b)
+    }
+
+    /// Remove the extension signals from `kex_algorithms`, as RFC 8308 only allows them in the
+    /// first key exchange
+    pub fn without_kex_extensions(mut self) -> Self {
+        self.kex_algorithms = self
+            .kex_algorithms
+            .into_iter()
+            .filter(|algorithm| !algorithm.is_extension_signal())
+            .collect::<Vec<_>>()
+            .into();
+
+        self
+    }
+
+    /// A proposal offering every algorithm this crate implements with the given cookie
+    pub fn with_default_algorithms(cookie: Cookie) -> Self {
+        let encryption_algorithms = || {
+            vec![
+                CipherType::ChaCha20Poly1305,
+                CipherType::AES256CTR,
+                CipherType::AES192CTR,
+                CipherType::AES128CTR,
+            ]
+            .into()
+        };
+        let mac_algorithms = || {
+            vec![
+                Mac::HmacSha256Etm,
+                Mac::HmacSha256,
+                Mac::HmacSha512,
+                Mac::HmacSha1,
+            ]
+            .into()
+        };
+
+        Self {
+            cookie,
+            kex_algorithms: vec![
+                KexAlgorithm::Curve25519Sha256,
+                KexAlgorithm::Curve25519Sha256Libssh,
+                KexAlgorithm::DiffieHellmanGroup14Sha256,
+            ]
+            .into(),
+            server_host_key_algorithms: vec![HostKeyAlgorithm::SshEd25519].into(),
+            encryption_algorithms_client_to_server: encryption_algorithms(),
+            encryption_algorithms_server_to_client: encryption_algorithms(),
+            mac_algorithms_client_to_server: mac_algorithms(),
+            mac_algorithms_server_to_client: mac_algorithms(),
+            compression_algorithms_client_to_server: vec![CompressionAlgorithm::None].into(),
+            compression_algorithms_server_to_client: vec![CompressionAlgorithm::None].into(),
+            languages_client_to_server: vec![].into(),
+            languages_server_to_client: vec![].into(),
+            first_kex_packet_follows: false,
+            reserved: 0,
         }
+    }
+}
 
-        encoded.append(&mut self.kex_algorithms.encode());
-        encoded.append(&mut self.server_host_key_algorithms.encode());
+impl Message for KexInitMessage {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
 
-        encoded.append(&mut self.encryption_algorithms_client_to_server.encode());
-        encoded.append(&mut self.encryption_algorithms_server_to_client.encode());
+        writer.put_u8(self.get_type() as u8);
+        writer.put_bytes(self.cookie.as_ref());
 
+        writer.put_name_list(&self.kex_algorithms)?;
+        writer.put_name_list(&self.server_host_key_algorithms)?;
 
-        encoded.append(&mut self.mac_algorithms_client_to_server.encode());
-        encoded.append(&mut self.mac_algorithms_server_to_client.encode());
+        writer.put_name_list(&self.encryption_algorithms_client_to_server)?;
+        writer.put_name_list(&self.encryption_algorithms_server_to_client)?;
 
+        writer.put_name_list(&self.mac_algorithms_client_to_server)?;
+        writer.put_name_list(&self.mac_algorithms_server_to_client)?;
 
-        encoded.append(&mut self.compression_algorithms_client_to_server.encode());
-        encoded.append(&mut self.compression_algorithms_server_to_client.encode());
+        writer.put_name_list(&self.compression_algorithms_client_to_server)?;
+        writer.put_name_list(&self.compression_algorithms_server_to_client)?;
 
-        encoded.append(&mut self.languages_client_to_server.encode());
-        encoded.append(&mut self.languages_server_to_client.encode());
+        writer.put_name_list(&self.languages_client_to_server)?;
+        writer.put_name_list(&self.languages_server_to_client)?;
 
-        encoded.push(match self.first_kex_packet_follows {
-            true => 1 as u8,
-            false => 0 as u8,
-        });
+        writer.put_bool(self.first_kex_packet_follows);
 
-        for b in (0 as u32).to_be_bytes() {
-            encoded.push(b as u8);
+        if self.reserved != 0 {
+            return Err(EncodeError::NonZeroReserved {
+                value: self.reserved,
+            }
+            .into());
         }
+        writer.put_u32(self.reserved);
 
-        encoded
+        Ok(())
     }
 
     fn get_type(&self) -> MessageType {
         MessageType::KexInit
     }
 
-    fn decode(data: Vec<u8>) -> Result<Self> {
-        if data.len() < 17 {
-            return Err("Packet to short".into())
-        } else {
-            todo!()
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::KexInit)?;
+
+        let mut cookie = [0; 16];
+        cookie.copy_from_slice(reader.get_bytes(16)?);
+
+        let message = Self {
+            cookie: Cookie::from_bytes(cookie),
+            kex_algorithms: reader.get_name_list()?.parse(),
+            server_host_key_algorithms: reader.get_name_list()?.parse(),
+
+            encryption_algorithms_client_to_server: reader.get_name_list()?.parse(),
+            encryption_algorithms_server_to_client: reader.get_name_list()?.parse(),
+
+            mac_algorithms_client_to_server: reader.get_name_list()?.parse(),
+            mac_algorithms_server_to_client: reader.get_name_list()?.parse(),
+
+            compression_algorithms_client_to_server: reader.get_name_list()?.parse(),
+            compression_algorithms_server_to_client: reader.get_name_list()?.parse(),
+
+            languages_client_to_server: languages(reader.get_name_list()?),
+            languages_server_to_client: languages(reader.get_name_list()?),
+
+            first_kex_packet_follows: reader.get_bool()?,
+            reserved: reader.get_u32()?,
+        };
+
+        reader.expect_end()?;
+
+        Ok(message)
+    }
+}
+
+/// The `SSH_MSG_NEWKEYS` message, sent by both sides to start using the newly exchanged keys
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NewKeys;
+
+impl Message for NewKeys {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        buffer.push(self.get_type() as u8);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::NewKeys
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::NewKeys)?;
+        reader.expect_end()?;
+
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::*;
+
+    #[test]
+    fn name_list_rejects_non_ascii_names() {
+        let names: NameList<String> = vec!["aes128-ctr".into(), "\u{e4}es128-ctr".into()].into();
+
+        assert_eq!(
+            names.encode(),
+            Err(EncodeError::NonAsciiName {
+                name: "\u{e4}es128-ctr".into()
+            })
+        );
+    }
+
+    #[test]
+    fn name_list_rejects_names_with_commas() {
+        let names = NameList::from(vec!["a,b".to_string()]);
+
+        assert_eq!(
+            names.encode(),
+            Err(EncodeError::CommaInName { name: "a,b".into() })
+        );
+    }
+
+    #[test]
+    fn name_list_decode() {
+        let (names, consumed) = NameList::decode(b"\0\0\0\x09zlib,noneextra").unwrap();
+
+        assert_eq!(names.to_string(), "zlib,none");
+        assert_eq!(consumed, 13);
+
+        let (names, consumed) = NameList::decode(b"\0\0\0\0").unwrap();
+
+        assert_eq!(names.to_string(), "");
+        assert_eq!(consumed, 4);
+    }
+
+    #[test]
+    fn name_list_iteration() {
+        let names: NameList<String> = vec!["zlib".into(), "none".into()].into();
+
+        assert_eq!(names.len(), 2);
+        assert!(!names.is_empty());
+        assert_eq!(names.iter().collect::<Vec<_>>(), ["zlib", "none"]);
+        assert_eq!((&names).into_iter().count(), 2);
+        assert_eq!(names.into_iter().collect::<Vec<_>>(), ["zlib", "none"]);
+
+        assert!(NameList::<String>::from(vec![]).is_empty());
+    }
+
+    #[test]
+    fn negotiate_uses_client_preference() {
+        let client: NameList<CipherType> = vec![
+            CipherType::ChaCha20Poly1305,
+            CipherType::AES256CTR,
+            CipherType::AES128CTR,
+        ]
+        .into();
+        let server: NameList<CipherType> =
+            vec![CipherType::AES128CTR, CipherType::AES256CTR].into();
+
+        assert_eq!(negotiate(&client, &server), Some(CipherType::AES256CTR));
+        assert_eq!(negotiate(&client, &vec![].into()), None);
+
+        assert_eq!(
+            negotiate_names("zlib,none", "none,zlib@openssh.com,zlib"),
+            Some("zlib")
+        );
+        assert_eq!(negotiate_names("zlib", "none"), None);
+        assert_eq!(negotiate_names("", ""), None);
+    }
+
+    #[test]
+    fn name_list_decode_errors() {
+        assert_eq!(
+            NameList::decode(b"\0\0").err(),
+            Some(DecodeError::UnexpectedEnd {
+                needed: 4,
+                available: 2
+            })
+        );
+        assert_eq!(
+            NameList::decode(b"\0\0\0\x09zlib").err(),
+            Some(DecodeError::UnexpectedEnd {
+                needed: 13,
+                available: 8
+            })
+        );
+
+        for list in [",zlib", "zlib,", "zlib,,none"] {
+            let mut data = (list.len() as u32).to_be_bytes().to_vec();
+            data.extend(list.bytes());
+
+            assert_eq!(
+                NameList::decode(&data).err(),
+                Some(DecodeError::EmptyName { list: list.into() })
+            );
+        }
+    }
+
+    #[test]
+    fn kex_algorithm_names() {
+        let algorithms = [
+            KexAlgorithm::Curve25519Sha256,
+            KexAlgorithm::Curve25519Sha256Libssh,
+            KexAlgorithm::EcdhSha2Nistp256,
+            KexAlgorithm::DiffieHellmanGroup16Sha512,
+            KexAlgorithm::DiffieHellmanGroup14Sha256,
+        ];
+
+        for algorithm in algorithms {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
         }
+
+        assert_eq!(
+            KexAlgorithm::Curve25519Sha256Libssh.to_string(),
+            "curve25519-sha256@libssh.org"
+        );
+    }
+
+    #[test]
+    fn host_key_algorithm_names() {
+        let algorithms = [
+            HostKeyAlgorithm::SshEd25519,
+            HostKeyAlgorithm::EcdsaSha2Nistp256,
+            HostKeyAlgorithm::RsaSha2_512,
+            HostKeyAlgorithm::RsaSha2_256,
+            HostKeyAlgorithm::SshRsa,
+        ];
+
+        for algorithm in algorithms {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+
+        assert_eq!(HostKeyAlgorithm::SshEd25519.to_string(), "ssh-ed25519");
+    }
+
+    #[test]
+    fn compression_algorithm_names() {
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::None] {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+
+        let names: NameList<CompressionAlgorithm> =
+            vec![CompressionAlgorithm::None, CompressionAlgorithm::Zstd].into();
+
+        assert_eq!(names.to_string(), "none,zstd@openssh.com");
+        assert_eq!(
+            names.encode(),
+            Ok(b"\0\0\0\x15none,zstd@openssh.com".to_vec())
+        );
+    }
+
+    fn sample_kex_init() -> KexInitMessage {
+        KexInitMessage {
+            cookie: Cookie::from_bytes([7; 16]),
+            kex_algorithms: vec![KexAlgorithm::Curve25519Sha256].into(),
+            server_host_key_algorithms: vec![HostKeyAlgorithm::SshEd25519].into(),
+            encryption_algorithms_client_to_server: vec![CipherType::AES128CTR].into(),
+            encryption_algorithms_server_to_client: vec![CipherType::AES128CTR].into(),
+            mac_algorithms_client_to_server: vec![Mac::HmacSha256].into(),
+            mac_algorithms_server_to_client: vec![Mac::HmacSha256].into(),
+            compression_algorithms_client_to_server: vec![CompressionAlgorithm::None].into(),
+            compression_algorithms_server_to_client: vec![
+                CompressionAlgorithm::None,
+                CompressionAlgorithm::Unknown("zlib".into()),
+            ]
+            .into(),
+            languages_client_to_server: vec![].into(),
+            languages_server_to_client: vec![].into(),
+            first_kex_packet_follows: false,
+            reserved: 0,
+        }
+    }
+
+    #[test]
+    fn kex_init_round_trip() {
+        let encoded = sample_kex_init().encode().unwrap();
+        let decoded = KexInitMessage::decode(&encoded).unwrap();
+
+        assert_eq!(decoded, sample_kex_init());
+        assert_eq!(decoded.cookie.to_bytes(), [7; 16]);
+        assert_eq!(
+            decoded.compression_algorithms_server_to_client.to_string(),
+            "none,zlib"
+        );
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn decode_any_message_dispatches_on_type() {
+        let messages: Vec<Box<dyn Message>> = vec![
+            Box::new(sample_kex_init()),
+            Box::new(NewKeys),
+            Box::new(ServiceRequest {
+                service: SSHService::UserAuth,
+            }),
+            Box::new(disconnect::DisconnectMessage {
+                reason: disconnect::DisconnectMessageType::ByApplication,
+                description: "bye".into(),
+                language_tag: "".into(),
+            }),
+            Box::new(userauth::UserauthRequest {
+                user_name: "user".into(),
+                service_name: SSHService::Connection,
+                method: userauth::UserauthMethod::Password {
+                    password: "hunter2".into(),
+                    new_password: None,
+                },
+            }),
+            Box::new(global::GlobalRequest {
+                request_name: "tcpip-forward".into(),
+                want_reply: true,
+                data: vec![0, 0, 0, 0, 0, 0, 0, 22],
+            }),
+            Box::new(channel::ChannelRequest {
+                recipient_channel: 1,
+                request_type: channel::ChannelRequestType::Exec {
+                    command: "ls".into(),
+                },
+                want_reply: false,
+            }),
+        ];
+
+        for message in messages {
+            let encoded = message.encode().unwrap();
+            let decoded = decode_any_message(&encoded).unwrap();
+
+            assert_eq!(decoded.get_type(), message.get_type());
+            assert_eq!(decoded.encode().unwrap(), encoded);
+
+            // Malformed input must give an error rather than a panic
+            for len in 0..encoded.len() {
+                let _ = decode_any_message(&encoded[..len]);
+            }
+            for i in 0..encoded.len() {
+                for value in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+                    let mut corrupted = encoded.clone();
+                    corrupted[i] = value;
+                    let _ = decode_any_message(&corrupted);
+                }
+            }
+        }
+
+        assert_eq!(
+            decode_any_message(&[]).err(),
+            Some(MessageError::Decode(DecodeError::UnexpectedEnd {
+                needed: 1,
+                available: 0
+            }))
+        );
+        assert_eq!(
+            decode_any_message(&[200]).err(),
+            Some(MessageError::Decode(DecodeError::UnknownMessageType {
+                actual: 200
+            }))
+        );
+    }
+
+    #[test]
+    fn empty_name_lists_encode_as_zero_length() {
+        assert_eq!(
+            NameList::<CipherType>::from(vec![]).encode(),
+            Ok(vec![0, 0, 0, 0])
+        );
+
+        let empty = KexInitMessage {
+            cookie: Cookie::from_bytes([7; 16]),
+            kex_algorithms: vec![].into(),
+            server_host_key_algorithms: vec![].into(),
+            encryption_algorithms_client_to_server: vec![].into(),
+            encryption_algorithms_server_to_client: vec![].into(),
+            mac_algorithms_client_to_server: vec![].into(),
+            mac_algorithms_server_to_client: vec![].into(),
+            compression_algorithms_client_to_server: vec![].into(),
+            compression_algorithms_server_to_client: vec![].into(),
+            languages_client_to_server: vec![].into(),
+            languages_server_to_client: vec![].into(),
+            first_kex_packet_follows: false,
+            reserved: 0,
+        };
+
+        // Type byte, cookie, ten zero-length name-lists, the boolean and the reserved u32
+        let mut expected = vec![MessageType::KexInit as u8];
+        expected.extend([7; 16]);
+        expected.extend([0; 10 * 4]);
+        expected.push(0);
+        expected.extend([0; 4]);
+
+        assert_eq!(empty.encode().unwrap(), expected);
+    }
+
+    #[test]
+    fn language_tags() {
+        for tag in ["en", "en-US", "i-klingon", "zh-Hant-TW", "x-private1", "sgn-BE-fr"] {
+            assert_eq!(Language::new(tag).unwrap().to_string(), tag);
+        }
+
+        for tag in ["", "en_US", "-en", "en-", "en--US", "e1", "toolongtag", "en-toolong12"] {
+            assert_eq!(
+                tag.parse::<Language>(),
+                Err(DecodeError::InvalidLanguageTag { tag: tag.into() })
+            );
+        }
+
+        // An empty list is the common case and is sent as a zero-length string
+        let none: NameList<Language> = vec![].into();
+        assert_eq!(none.encode(), Ok(b"\0\0\0\0".to_vec()));
+    }
+
+    #[test]
+    fn kex_init_languages_round_trip() {
+        let kex_init = KexInitMessage {
+            languages_server_to_client: vec![
+                Language::new("en-US").unwrap(),
+                Language::new("de").unwrap(),
+            ]
+            .into(),
+            ..sample_kex_init()
+        };
+
+        let decoded = KexInitMessage::decode(&kex_init.encode().unwrap()).unwrap();
+        assert!(decoded.languages_client_to_server.is_empty());
+        assert_eq!(decoded.languages_server_to_client.to_string(), "en-US,de");
+
+        // An invalid tag from the peer is kept rather than failing the whole message
+        let mut invalid = sample_kex_init();
+        invalid.languages_client_to_server = vec![Language::Unknown("en_US".into())].into();
+        let decoded = KexInitMessage::decode(&invalid.encode().unwrap()).unwrap();
+        assert_eq!(
+            decoded.languages_client_to_server,
+            vec![Language::Unknown("en_US".into())].into()
+        );
+    }
+
+    #[test]
+    fn negotiated_cipher_can_be_instantiated() {
+        let mut server = sample_kex_init();
+        server.encryption_algorithms_client_to_server = vec![
+            CipherType::Unknown("aes256-gcm@example.com".into()),
+            CipherType::AES256CTR,
+        ]
+        .into();
+        let client = KexInitMessage {
+            encryption_algorithms_client_to_server: vec![
+                CipherType::ChaCha20Poly1305,
+                CipherType::AES256CTR,
+            ]
+            .into(),
+            ..sample_kex_init()
+        };
+
+        let server = KexInitMessage::decode(&server.encode().unwrap()).unwrap();
+        let cipher = negotiate(
+            &client.encryption_algorithms_client_to_server,
+            &server.encryption_algorithms_client_to_server,
+        )
+        .unwrap();
+
+        assert_eq!(cipher, CipherType::AES256CTR);
+        assert_eq!(
+            cipher.instantiate(&[1; 32], &[2; 16]).unwrap().name(),
+            "aes256-ctr"
+        );
+    }
+
+    #[test]
+    fn default_kex_init_negotiates() {
+        let client = KexInitMessage::with_default_algorithms(Cookie::from_bytes([1; 16]));
+        let server = KexInitMessage::with_default_algorithms(Cookie::from_bytes([2; 16]));
+        let server = KexInitMessage::decode(&server.encode().unwrap()).unwrap();
+
+        assert_eq!(
+            negotiate(&client.kex_algorithms, &server.kex_algorithms),
+            Some(KexAlgorithm::Curve25519Sha256)
+        );
+        assert_eq!(
+            negotiate(
+                &client.encryption_algorithms_client_to_server,
+                &server.encryption_algorithms_client_to_server
+            ),
+            Some(CipherType::ChaCha20Poly1305)
+        );
+        assert_eq!(
+            negotiate(
+                &client.mac_algorithms_server_to_client,
+                &server.mac_algorithms_server_to_client
+            ),
+            Some(Mac::HmacSha256Etm)
+        );
+        assert_eq!(server.cookie, Cookie::from_bytes([2; 16]));
+    }
+
+    #[test]
+    fn kex_init_guess_matches() {
+        let client = KexInitMessage::with_default_algorithms(Cookie::from_bytes([1; 16]));
+        let mut server = KexInitMessage::with_default_algorithms(Cookie::from_bytes([2; 16]));
+        assert!(client.guess_matches(&server));
+        assert!(server.guess_matches(&client));
+
+        // Only the first preferences matter, not whether the lists are the same
+        server.kex_algorithms = vec![KexAlgorithm::Curve25519Sha256].into();
+        assert!(client.guess_matches(&server));
+
+        server.kex_algorithms = vec![
+            KexAlgorithm::Curve25519Sha256Libssh,
+            KexAlgorithm::Curve25519Sha256,
+        ]
+        .into();
+        assert!(!client.guess_matches(&server));
+
+        server.kex_algorithms = vec![KexAlgorithm::Curve25519Sha256].into();
+        server.server_host_key_algorithms =
+            vec![HostKeyAlgorithm::RsaSha2_512, HostKeyAlgorithm::SshEd25519].into();
+        assert!(!client.guess_matches(&server));
+
+        server.server_host_key_algorithms = vec![].into();
+        assert!(!client.guess_matches(&server));
+    }
+
+    #[test]
+    fn kex_extension_signals_are_not_negotiated() {
+        assert_eq!("ext-info-c".parse(), Ok(KexAlgorithm::ExtInfoC));
+        assert_eq!(KexAlgorithm::ExtInfoS.to_string(), "ext-info-s");
+
+        let client = KexInitMessage::with_default_algorithms(Cookie::from_bytes([1; 16]))
+            .with_kex_extension(KexAlgorithm::ExtInfoC);
+        let names = client.kex_algorithms.to_string();
+        assert!(names.ends_with(",ext-info-c"));
+        assert!(!client
+            .without_kex_extensions()
+            .kex_algorithms
+            .to_string()
+            .contains("ext-info"));
+
+        // Even a peer that only lists the signal doesn't have a key exchange method in common
+        let client: NameList<KexAlgorithm> =
+            vec![KexAlgorithm::ExtInfoC, KexAlgorithm::Curve25519Sha256].into();
+        let server: NameList<KexAlgorithm> =
+            vec![KexAlgorithm::ExtInfoC, KexAlgorithm::Curve25519Sha256].into();
+        assert_eq!(
+            negotiate_kex(&client, &server),
+            Some(KexAlgorithm::Curve25519Sha256)
+        );
+        let server: NameList<KexAlgorithm> = vec![KexAlgorithm::ExtInfoC].into();
+        assert_eq!(negotiate_kex(&client, &server), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn default_kex_init_cookies_are_random() {
+        assert_ne!(
+            KexInitMessage::default_client().cookie,
+            KexInitMessage::default_server().cookie
+        );
+    }
+
+    #[test]
+    fn kex_init_decode_checks_framing() {
+        let encoded = sample_kex_init().encode().unwrap();
+
+        // The payload must start with the type byte, just like `encode` produces
+        assert!(matches!(
+            KexInitMessage::decode(&encoded[1..]),
+            Err(MessageError::Decode(
+                DecodeError::UnexpectedMessageType { .. }
+            ))
+        ));
+
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(matches!(
+            KexInitMessage::decode(&trailing),
+            Err(MessageError::Decode(DecodeError::TrailingBytes {
+                count: 1
+            }))
+        ));
+
+        assert!(matches!(
+            KexInitMessage::decode(&encoded[..encoded.len() - 1]),
+            Err(MessageError::Decode(DecodeError::UnexpectedEnd { .. }))
+        ));
+    }
+
+    #[test]
+    fn kex_init_first_kex_packet_follows_accepts_any_nonzero_value() {
+        let mut encoded = sample_kex_init().encode().unwrap();
+        let length = encoded.len();
+
+        encoded[length - 5] = 0x02;
+        assert!(
+            KexInitMessage::decode(&encoded)
+                .unwrap()
+                .first_kex_packet_follows
+        );
+
+        encoded[length - 5] = 0x00;
+        assert!(
+            !KexInitMessage::decode(&encoded)
+                .unwrap()
+                .first_kex_packet_follows
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn kex_init_random_cookie() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        assert_ne!(Cookie::random(), Cookie::from_bytes([0; 16]));
+        assert_ne!(Cookie::random(), Cookie::random());
+
+        // An injected rng gives deterministic cookies for tests
+        assert_eq!(
+            Cookie::random_with_rng(&mut StdRng::seed_from_u64(0)),
+            Cookie::random_with_rng(&mut StdRng::seed_from_u64(0))
+        );
+
+        let cookie = Cookie::from_bytes([3; 16]);
+        assert_eq!(cookie.as_ref(), &[3; 16]);
+        assert_eq!(cookie.to_bytes(), [3; 16]);
+    }
+
+    #[test]
+    fn kex_init_reserved() {
+        let mut encoded = sample_kex_init().encode().unwrap();
+        let length = encoded.len();
+        encoded[length - 4..].copy_from_slice(&[0, 0, 0, 5]);
+
+        // Whatever the peer sent is kept, but it can't be sent back
+        let decoded = KexInitMessage::decode(&encoded).unwrap();
+        assert_eq!(decoded.reserved, 5);
+        assert_eq!(
+            decoded.encode(),
+            Err(MessageError::Encode(EncodeError::NonZeroReserved {
+                value: 5
+            }))
+        );
+    }
+
+    #[test]
+    fn new_keys() {
+        assert_eq!(NewKeys.encode().unwrap(), [21]);
+        assert_eq!(NewKeys::decode(&[21]), Ok(NewKeys));
+        assert_eq!(
+            NewKeys::decode(&[21, 0]),
+            Err(MessageError::Decode(DecodeError::TrailingBytes { count: 1 }))
+        );
+        assert!(NewKeys::decode(&[20]).is_err());
+        assert!(NewKeys::decode(&[]).is_err());
+    }
+
+    #[test]
+    fn encode_into_reuses_buffer() {
+        let mut buffer = vec![];
+
+        NewKeys.encode_into(&mut buffer).unwrap();
+        sample_kex_init().encode_into(&mut buffer).unwrap();
+
+        let kex_init = sample_kex_init().encode().unwrap();
+        assert_eq!(buffer[0], 21);
+        assert_eq!(buffer[1..], kex_init);
+
+        buffer.clear();
+        NewKeys.encode_into(&mut buffer).unwrap();
+        assert_eq!(buffer, [21]);
+    }
+
+    #[test]
+    fn service_request_round_trip() {
+        let request = ServiceRequest {
+            service: SSHService::UserAuth,
+        };
+
+        let encoded = request.encode().unwrap();
+
+        assert_eq!(encoded, b"\x05\0\0\0\x0cssh-userauth");
+        assert!(matches!(
+            ServiceRequest::decode(&encoded),
+            Ok(ServiceRequest {
+                service: SSHService::UserAuth
+            })
+        ));
+    }
+
+    #[test]
+    fn name_list_encode() {
+        let names: NameList<String> = vec!["zlib".into(), "none".into()].into();
+
+        assert_eq!(names.encode(), Ok(b"\0\0\0\x09zlib,none".to_vec()));
+    }
+
+    #[test]
+    fn name_list_display() {
+        let names: NameList<String> = vec!["a".into(), "b".into(), "c".into()].into();
+        assert_eq!(names.to_string(), "a,b,c");
+
+        let names: NameList<String> = vec!["a".into()].into();
+        assert_eq!(names.to_string(), "a");
+
+        let names: NameList<String> = vec![].into();
+        assert_eq!(names.to_string(), "");
     }
 }
@@ -0,0 +1,489 @@
+use super::{
+    message_type::MessageType, take_message_type, DecodeError, Message, MessageError, NameList,
+    SSHService,
+};
+use crate::codec::{Reader, Writer};
+use crate::prelude::*;
+
+/// The authentication method used in a `UserauthRequest`, as defined by RFC 4252
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UserauthMethod {
+    /// Used to find out which methods the server will accept
+    None,
+    Password {
+        password: String,
+        /// The new password when responding to a password change request
+        new_password: Option<String>,
+    },
+    PublicKey {
+        /// The public key algorithm name, such as `ssh-ed25519`
+        algorithm: String,
+        /// The public key blob in the format used by the algorithm
+        public_key: Vec<u8>,
+        /// The signature proving possession of the private key, left out to check whether the
+        /// key would be accepted
+        signature: Option<Vec<u8>>,
+    },
+}
+
+impl UserauthMethod {
+    /// The method name sent over the wire
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Password { .. } => "password",
+            Self::PublicKey { .. } => "publickey",
+        }
+    }
+}
+
+/// The `SSH_MSG_USERAUTH_REQUEST` message, sent by the client to authenticate
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserauthRequest {
+    pub user_name: String,
+    /// The service to start once authentication succeeds
+    pub service_name: SSHService,
+    pub method: UserauthMethod,
+}
+
+impl UserauthRequest {
+    /// The data the client signs with its private key for a `publickey` request, as described in
+    /// RFC 4252 section 7
+    ///
+    /// This is the session id followed by the fields of the request up to the signature.
+    pub fn publickey_signed_data(
+        session_id: &[u8],
+        user_name: &str,
+        service_name: &SSHService,
+        algorithm: &str,
+        public_key: &[u8],
+    ) -> Vec<u8> {
+        let mut writer = Writer::new();
+
+        writer.put_string(session_id);
+        writer.put_u8(MessageType::UserauthRequest as u8);
+        writer.put_string(user_name.as_bytes());
+        writer.put_string(service_name.to_string().as_bytes());
+        writer.put_string(b"publickey");
+        writer.put_bool(true);
+        writer.put_string(algorithm.as_bytes());
+        writer.put_string(public_key);
+
+        writer.into_bytes()
+    }
+
+    /// A `publickey` request carrying the signature of `publickey_signed_data` for the same fields
+    pub fn signed_publickey(
+        user_name: String,
+        service_name: SSHService,
+        algorithm: String,
+        public_key: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> Self {
+        Self {
+            user_name,
+            service_name,
+            method: UserauthMethod::PublicKey {
+                algorithm,
+                public_key,
+                signature: Some(signature),
+            },
+        }
+    }
+}
+
+impl Message for UserauthRequest {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(self.user_name.as_bytes());
+        writer.put_string(self.service_name.to_string().as_bytes());
+        writer.put_string(self.method.name().as_bytes());
+
+        match &self.method {
+            UserauthMethod::None => {}
+            UserauthMethod::Password {
+                password,
+                new_password,
+            } => {
+                writer.put_bool(new_password.is_some());
+                writer.put_string(password.as_bytes());
+
+                if let Some(new_password) = new_password {
+                    writer.put_string(new_password.as_bytes());
+                }
+            }
+            UserauthMethod::PublicKey {
+                algorithm,
+                public_key,
+                signature,
+            } => {
+                writer.put_bool(signature.is_some());
+                writer.put_string(algorithm.as_bytes());
+                writer.put_string(public_key);
+
+                if let Some(signature) = signature {
+                    writer.put_string(signature);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::UserauthRequest
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::UserauthRequest)?;
+        let user_name = reader.get_utf8()?;
+        let service_name = reader.get_utf8()?;
+
+        let method = match reader.get_utf8()?.as_str() {
+            "none" => UserauthMethod::None,
+            "password" => {
+                let change = reader.get_bool()?;
+                let password = reader.get_utf8()?;
+
+                UserauthMethod::Password {
+                    password,
+                    new_password: match change {
+                        true => Some(reader.get_utf8()?),
+                        false => None,
+                    },
+                }
+            }
+            "publickey" => {
+                let signed = reader.get_bool()?;
+                let algorithm = reader.get_utf8()?;
+                let public_key = reader.get_string()?.to_vec();
+
+                UserauthMethod::PublicKey {
+                    algorithm,
+                    public_key,
+                    signature: match signed {
+                        true => Some(reader.get_string()?.to_vec()),
+                        false => None,
+                    },
+                }
+            }
+            name => return Err(DecodeError::UnknownUserauthMethod { name: name.into() }.into()),
+        };
+
+        reader.expect_end()?;
+
+        Ok(Self {
+            user_name,
+            service_name: service_name.parse().unwrap(),
+            method,
+        })
+    }
+}
+
+/// The `SSH_MSG_USERAUTH_FAILURE` message, sent when a request was rejected or needs more methods
+pub struct UserauthFailure {
+    /// The methods that may be used to continue authenticating
+    pub available_methods: NameList<String>,
+    /// Whether the request succeeded but more authentication is still needed
+    pub partial_success: bool,
+}
+
+impl Message for UserauthFailure {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_name_list(&self.available_methods)?;
+        writer.put_bool(self.partial_success);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::UserauthFailure
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::UserauthFailure)?;
+        let available_methods = reader.get_name_list()?;
+        let partial_success = reader.get_bool()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            available_methods,
+            partial_success,
+        })
+    }
+}
+
+/// The `SSH_MSG_USERAUTH_SUCCESS` message, sent once authentication is complete
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserauthSuccess;
+
+impl Message for UserauthSuccess {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        buffer.push(self.get_type() as u8);
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::UserauthSuccess
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::UserauthSuccess)?;
+        reader.expect_end()?;
+
+        Ok(Self)
+    }
+}
+
+/// The `SSH_MSG_USERAUTH_BANNER` message, text the server wants shown before authentication
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserauthBanner {
+    pub message: String,
+    /// The RFC 3066 language tag of the message, usually empty
+    pub language_tag: String,
+}
+
+impl Message for UserauthBanner {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(self.message.as_bytes());
+        writer.put_string(self.language_tag.as_bytes());
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::UserauthBanner
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::UserauthBanner)?;
+        let message = reader.get_utf8()?;
+        let language_tag = reader.get_utf8()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            message,
+            language_tag,
+        })
+    }
+}
+
+/// The `SSH_MSG_USERAUTH_PASSWD_CHANGEREQ` message, sent by the server when the password has
+/// expired
+///
+/// The client should send another `password` request with both the old password and a new one
+/// in `new_password`. Message number 60 is specific to the `password` method, so this should only
+/// be decoded in reply to a password request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PasswdChangeReq {
+    pub prompt: String,
+    /// The RFC 3066 language tag of the prompt, usually empty
+    pub language_tag: String,
+}
+
+impl Message for PasswdChangeReq {
+    fn encode_into(&self, buffer: &mut Vec<u8>) -> Result<(), MessageError> {
+        let mut writer = Writer::append_to(buffer);
+
+        writer.put_u8(self.get_type() as u8);
+        writer.put_string(self.prompt.as_bytes());
+        writer.put_string(self.language_tag.as_bytes());
+
+        Ok(())
+    }
+
+    fn get_type(&self) -> MessageType {
+        MessageType::UserauthPasswdChangeReq
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader::new(data);
+
+        take_message_type(&mut reader, MessageType::UserauthPasswdChangeReq)?;
+        let prompt = reader.get_utf8()?;
+        let language_tag = reader.get_utf8()?;
+        reader.expect_end()?;
+
+        Ok(Self {
+            prompt,
+            language_tag,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::userauth::*;
+
+    fn request(method: UserauthMethod) -> UserauthRequest {
+        UserauthRequest {
+            user_name: "user".into(),
+            service_name: SSHService::Connection,
+            method,
+        }
+    }
+
+    #[test]
+    fn none_request() {
+        let message = request(UserauthMethod::None);
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(
+            encoded,
+            b"\x32\0\0\0\x04user\0\0\0\x0essh-connection\0\0\0\x04none".to_vec()
+        );
+        assert_eq!(UserauthRequest::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn password_request_round_trip() {
+        let message = request(UserauthMethod::Password {
+            password: "hunter2".into(),
+            new_password: None,
+        });
+
+        let encoded = message.encode().unwrap();
+
+        assert!(encoded.ends_with(b"\0\0\0\x08password\0\0\0\0\x07hunter2"));
+        assert_eq!(UserauthRequest::decode(&encoded).unwrap(), message);
+
+        let message = request(UserauthMethod::Password {
+            password: "hunter2".into(),
+            new_password: Some("hunter3".into()),
+        });
+
+        let encoded = message.encode().unwrap();
+
+        assert!(encoded.ends_with(b"\0\0\0\x08password\x01\0\0\0\x07hunter2\0\0\0\x07hunter3"));
+        assert_eq!(UserauthRequest::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn publickey_request_round_trip() {
+        for signature in [None, Some(vec![4, 5, 6])] {
+            let message = request(UserauthMethod::PublicKey {
+                algorithm: "ssh-ed25519".into(),
+                public_key: vec![1, 2, 3],
+                signature,
+            });
+
+            let encoded = message.encode().unwrap();
+
+            assert_eq!(UserauthRequest::decode(&encoded).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn publickey_signed_data_layout() {
+        let session_id = [0xaa; 32];
+
+        let data = UserauthRequest::publickey_signed_data(
+            &session_id,
+            "user",
+            &SSHService::Connection,
+            "ssh-ed25519",
+            &[1, 2, 3],
+        );
+
+        let mut expected = vec![0, 0, 0, 32];
+        expected.extend(session_id);
+        expected.extend(b"\x32\0\0\0\x04user\0\0\0\x0essh-connection\0\0\0\x09publickey\x01");
+        expected.extend(b"\0\0\0\x0bssh-ed25519\0\0\0\x03\x01\x02\x03");
+        assert_eq!(data, expected);
+
+        // The request itself has the same fields after the type byte, followed by the signature
+        let message = UserauthRequest::signed_publickey(
+            "user".into(),
+            SSHService::Connection,
+            "ssh-ed25519".into(),
+            vec![1, 2, 3],
+            vec![4, 5],
+        );
+        let encoded = message.encode().unwrap();
+        assert_eq!(encoded[..encoded.len() - 6], data[36..]);
+        assert_eq!(encoded[encoded.len() - 6..], [0, 0, 0, 2, 4, 5]);
+    }
+
+    #[test]
+    fn unknown_method() {
+        let encoded = b"\x32\0\0\0\x04user\0\0\0\x0essh-connection\0\0\0\x09hostbased";
+
+        assert_eq!(
+            UserauthRequest::decode(encoded),
+            Err(MessageError::Decode(DecodeError::UnknownUserauthMethod {
+                name: "hostbased".into()
+            }))
+        );
+    }
+
+    #[test]
+    fn failure_round_trip() {
+        let message = UserauthFailure {
+            available_methods: vec!["publickey".into(), "password".into()].into(),
+            partial_success: true,
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x33\0\0\0\x12publickey,password\x01".to_vec());
+
+        let decoded = UserauthFailure::decode(&encoded).unwrap();
+        assert_eq!(decoded.available_methods.to_string(), "publickey,password");
+        assert!(decoded.partial_success);
+    }
+
+    #[test]
+    fn success_round_trip() {
+        assert_eq!(UserauthSuccess.encode().unwrap(), [52]);
+        assert_eq!(UserauthSuccess::decode(&[52]), Ok(UserauthSuccess));
+        assert!(UserauthSuccess::decode(&[52, 0]).is_err());
+    }
+
+    #[test]
+    fn banner_round_trip() {
+        let message = UserauthBanner {
+            message: "welcome".into(),
+            language_tag: "".into(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(encoded, b"\x35\0\0\0\x07welcome\0\0\0\0".to_vec());
+        assert_eq!(UserauthBanner::decode(&encoded).unwrap(), message);
+    }
+
+    #[test]
+    fn passwd_change_req_round_trip() {
+        let message = PasswdChangeReq {
+            prompt: "Password expired".into(),
+            language_tag: "en".into(),
+        };
+
+        let encoded = message.encode().unwrap();
+
+        assert_eq!(
+            encoded,
+            b"\x3c\0\0\0\x10Password expired\0\0\0\x02en".to_vec()
+        );
+        assert_eq!(PasswdChangeReq::decode(&encoded).unwrap(), message);
+        assert!(PasswdChangeReq::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -1,10 +1,38 @@
+mod packet_error;
+
+pub use packet_error::*;
+
+use crate::compression::Compression;
 use crate::encryption::Cipher;
-use crate::mac::Mac;
+use crate::mac::{Authenticator, Mac};
+use crate::prelude::*;
+
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// The largest `packet_length` we are willing to accept, as recommended by RFC 4253 section 6.1
+pub const MAX_PACKET_LENGTH: usize = 35000;
 
 pub struct Packet {
     payload: Vec<u8>,
     mac_type: Mac,
+    mac_key: Zeroizing<Vec<u8>>,
     encryption_cipher: Box<dyn Cipher>,
+    sequence_number: u32,
+}
+
+impl core::fmt::Debug for Packet {
+    /// Shows the shape of the packet but not the payload or mac key, so that packets can be
+    /// logged without leaking their contents
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Packet")
+            .field("payload_len", &self.payload.len())
+            .field("mac_type", &self.mac_type)
+            .field("cipher", &self.encryption_cipher)
+            .field("block_size", &self.encryption_cipher.get_block_size())
+            .field("sequence_number", &self.sequence_number)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Packet {
@@ -12,29 +40,1045 @@ impl Packet {
         Packet {
             payload,
             mac_type,
+            mac_key: Zeroizing::new(vec![]),
             encryption_cipher: cipher,
+            sequence_number: 0,
+        }
+    }
+
+    /// Set the sequence number of the packet, which is used by AEAD ciphers and macs
+    pub fn set_sequence_number(&mut self, sequence_number: u32) {
+        self.sequence_number = sequence_number;
+    }
+
+    /// Set the key used to compute the mac of the packet
+    pub fn set_mac_key(&mut self, mac_key: Vec<u8>) {
+        self.mac_key = Zeroizing::new(mac_key);
+    }
+
+    /// Take back the cipher, which keeps its state for the next packet
+    pub fn into_cipher(self) -> Box<dyn Cipher> {
+        self.encryption_cipher
+    }
+
+    /// Decode a binary packet from the start of `data`
+    ///
+    /// Returns the payload of the packet along with the total number of bytes used from `data`
+    /// (including the mac). Packets encrypted with an AEAD cipher are authenticated and decrypted
+    /// using the sequence number, for any other cipher `data` must already be decrypted.
+    ///
+    /// For ciphers other than AEAD ones the mac is not verified here, `mac` is only used to
+    /// know how many bytes it takes up. The caller has to check it, use `read_framed` to read
+    /// packets that are both decrypted and authenticated.
+    pub fn decode(
+        data: &[u8],
+        cipher: &dyn Cipher,
+        mac: &Mac,
+        sequence_number: u32,
+    ) -> Result<(Vec<u8>, usize), PacketError> {
+        if data.len() < 5 {
+            return Err(PacketError::UnexpectedEnd {
+                needed: 5,
+                available: data.len(),
+            });
+        }
+
+        let length_field = [data[0], data[1], data[2], data[3]];
+        let aead = cipher.as_aead();
+
+        let (packet_length, tag_length) = match aead {
+            Some(aead) => (
+                aead.decrypt_length(sequence_number, length_field) as usize,
+                aead.tag_len(),
+            ),
+            None => (u32::from_be_bytes(length_field) as usize, mac.output_len()),
+        };
+
+        if packet_length > MAX_PACKET_LENGTH {
+            return Err(PacketError::TooLarge { len: packet_length });
+        }
+
+        let block_size = cipher.get_block_size().max(8);
+        // AEAD ciphers and encrypt-then-mac don't include the length field when aligning to the
+        // block size
+        let aligned_length = if aead.is_some() || mac.is_etm() {
+            packet_length
+        } else {
+            4 + packet_length
+        };
+        if aligned_length % block_size != 0 {
+            return Err(PacketError::InvalidLength { len: packet_length });
+        }
+
+        let consumed = 4 + packet_length + tag_length;
+        if data.len() < consumed {
+            return Err(PacketError::UnexpectedEnd {
+                needed: consumed,
+                available: data.len(),
+            });
+        }
+
+        let mut packet = data[..4 + packet_length].to_vec();
+        if let Some(aead) = aead {
+            aead.open(
+                sequence_number,
+                &mut packet,
+                &data[4 + packet_length..consumed],
+            )?;
         }
+
+        // There must be at least 4 bytes of padding and it must leave room for a payload of at
+        // least the message type byte, otherwise slicing out the payload would go wrong
+        let padding_length = match packet.get(4) {
+            Some(&padding_length) => padding_length as usize,
+            None => return Err(PacketError::InvalidLength { len: packet_length }),
+        };
+        if padding_length < 4 || padding_length + 1 >= packet_length {
+            return Err(PacketError::InvalidPadding { padding_length });
+        }
+
+        let payload = packet[5..4 + packet_length - padding_length].to_vec();
+
+        Ok((payload, consumed))
+    }
+
+    /// Decode a binary packet like `decode` and decompress its payload, the mac is not verified
+    pub fn decode_compressed(
+        data: &[u8],
+        cipher: &dyn Cipher,
+        mac: &Mac,
+        sequence_number: u32,
+        compression: &Compression,
+    ) -> Result<(Vec<u8>, usize), PacketError> {
+        let (payload, consumed) = Self::decode(data, cipher, mac, sequence_number)?;
+
+        Ok((compression.decompress(&payload)?, consumed))
     }
 
-    pub fn encode(&self) -> Vec<u8> {
-        let mut encoded_packet = vec![];
+    /// Read exactly one binary packet from `reader` and return its payload
+    ///
+    /// For most ciphers the packet length is encrypted, so the first block is read and decrypted
+    /// to find out how much more to read. The mac or authentication tag is checked before the
+    /// payload is returned. `cipher` keeps its state for the next packet. Short reads are retried
+    /// until the whole packet has arrived, but a stream that ends part way through a packet gives
+    /// `PacketError::ReadError`.
+    #[cfg(feature = "std")]
+    pub fn read_framed<R: std::io::Read>(
+        reader: &mut R,
+        cipher: &mut dyn Cipher,
+        mac: &Mac,
+        mac_key: &[u8],
+        sequence_number: u32,
+    ) -> Result<Vec<u8>, PacketError> {
+        let data = Self::read_decrypted(reader, cipher, mac, mac_key, sequence_number)?;
+        let (payload, _) = Self::decode(&data, cipher, mac, sequence_number)?;
+
+        Ok(payload)
+    }
+
+    /// Read one binary packet like `read_framed`, returning the whole packet with everything but
+    /// an AEAD cipher's encryption removed so that it can be passed to `decode`
+    #[cfg(feature = "std")]
+    pub(crate) fn read_decrypted<R: std::io::Read>(
+        reader: &mut R,
+        cipher: &mut dyn Cipher,
+        mac: &Mac,
+        mac_key: &[u8],
+        sequence_number: u32,
+    ) -> Result<Vec<u8>, PacketError> {
+        let mut read_exact = |buffer: &mut [u8]| {
+            reader
+                .read_exact(buffer)
+                .map_err(|e| PacketError::ReadError { kind: e.kind() })
+        };
+        let verify_mac =
+            |packet: &[u8], received: &[u8]| mac.check(mac_key, sequence_number, packet, received);
+
+        let data = match cipher.as_aead() {
+            Some(aead) => {
+                let mut data = vec![0; 4];
+                read_exact(&mut data)?;
+
+                let length =
+                    aead.decrypt_length(sequence_number, [data[0], data[1], data[2], data[3]]);
+                let length = check_length(length)?;
+
+                data.resize(4 + length + aead.tag_len(), 0);
+                read_exact(&mut data[4..])?;
 
-        encoded_packet.append(&mut (self.payload.len() as u32).to_le_bytes().to_vec());
+                data
+            }
+            None if mac.is_etm() => {
+                let mut data = vec![0; 4];
+                read_exact(&mut data)?;
 
-        let padding_length = if 8 > self.encryption_cipher.get_block_size() {
-            self.payload.len() % 8
+                let length =
+                    check_length(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))?;
+
+                data.resize(4 + length + mac.output_len(), 0);
+                read_exact(&mut data[4..])?;
+
+                // The mac covers the encrypted packet, so it is checked before decrypting
+                verify_mac(&data[..4 + length], &data[4 + length..])?;
+                cipher.decrypt(&mut data[4..4 + length])?;
+
+                data
+            }
+            None => {
+                // The length is encrypted, so the first block has to be decrypted to find it
+                let block_size = cipher.get_block_size().max(8);
+
+                let mut data = vec![0; block_size];
+                read_exact(&mut data)?;
+                cipher.decrypt(&mut data)?;
+
+                let length =
+                    check_length(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))?;
+                if 4 + length < block_size {
+                    return Err(PacketError::InvalidLength { len: length });
+                }
+
+                data.resize(4 + length + mac.output_len(), 0);
+                read_exact(&mut data[block_size..])?;
+                cipher.decrypt(&mut data[block_size..4 + length])?;
+
+                verify_mac(&data[..4 + length], &data[4 + length..])?;
+
+                data
+            }
+        };
+
+        Ok(data)
+    }
+
+    /// The number of bytes of random padding needed for this packet
+    ///
+    /// The length, padding length, payload and padding together must be a multiple of the cipher
+    /// block size (or 8, whichever is larger) and there must be at least 4 bytes of padding. AEAD
+    /// ciphers and encrypt-then-mac leave the length field out of the alignment, as it is sent
+    /// unencrypted.
+    pub fn padding_length(&self) -> usize {
+        // Stream ciphers and the none cipher report a block size of 1, but RFC 4253 still
+        // requires packets to be aligned to at least 8 bytes
+        let block_size = self.encryption_cipher.get_block_size().max(8);
+
+        let aligned_length = if self.encryption_cipher.as_aead().is_some() || self.mac_type.is_etm()
+        {
+            1 + self.payload.len()
         } else {
-            self.payload.len() % self.encryption_cipher.get_block_size()
+            5 + self.payload.len()
+        };
+
+        let mut padding_length = block_size - (aligned_length % block_size);
+        if padding_length < 4 {
+            padding_length += block_size;
+        }
+
+        padding_length
+    }
+
+    /// The number of bytes `encode` will produce for this packet, including the mac or
+    /// authentication tag
+    pub fn encoded_len(&self) -> usize {
+        let tag_length = match self.encryption_cipher.as_aead() {
+            Some(aead) => aead.tag_len(),
+            None => self.mac_type.output_len(),
+        };
+
+        4 + 1 + self.payload.len() + self.padding_length() + tag_length
+    }
+
+    /// Encode the packet in the binary packet format from RFC 4253 section 6, using the operating
+    /// system's random number generator for the padding
+    #[cfg(feature = "std")]
+    pub fn encode(&mut self) -> Result<Vec<u8>, PacketError> {
+        self.encode_with_rng(&mut rand::rngs::OsRng)
+    }
+
+    /// Append the encoded packet to `buffer`, using the operating system's random number
+    /// generator for the padding, see `encode_into_with_rng`
+    #[cfg(feature = "std")]
+    pub fn encode_into(&mut self, buffer: &mut Vec<u8>) -> Result<(), PacketError> {
+        self.encode_into_with_rng(&mut rand::rngs::OsRng, buffer)
+    }
+
+    /// Compress the payload and encode the packet like `encode`, the counterpart of
+    /// `decode_compressed`
+    #[cfg(feature = "std")]
+    pub fn encode_compressed(&mut self, compression: &Compression) -> Result<Vec<u8>, PacketError> {
+        self.encode_compressed_with_rng(&mut rand::rngs::OsRng, compression)
+    }
+
+    /// Compress the payload and encode the packet, filling the random padding from `rng`
+    ///
+    /// The packet keeps its uncompressed payload, so encoding it again compresses it again from
+    /// the original rather than compressing twice. The payload is sent unchanged while
+    /// compression isn't active.
+    pub fn encode_compressed_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        compression: &Compression,
+    ) -> Result<Vec<u8>, PacketError> {
+        // The padding depends on the compressed length, so the compressed payload is encoded in
+        // place of the original
+        let compressed = compression.compress(&self.payload)?;
+        let payload = core::mem::replace(&mut self.payload, compressed);
+        let encoded = self.encode_with_rng(rng);
+        self.payload = payload;
+
+        encoded
+    }
+
+    /// Encode the packet, filling the random padding from `rng`
+    pub fn encode_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, PacketError> {
+        let mut buffer = Vec::with_capacity(self.encoded_len());
+        self.encode_into_with_rng(rng, &mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    /// Append the encoded packet to `buffer`, filling the random padding from `rng`
+    ///
+    /// Reusing one buffer for many packets avoids allocating for each of them. If an error is
+    /// returned `buffer` is left as it was.
+    ///
+    /// The encoded packet is laid out as
+    /// `packet_length || padding_length || payload || random padding || mac`, with everything but
+    /// the mac encrypted with the packet's cipher. The mac is normally computed over the plaintext
+    /// packet, but encrypt-then-mac variants leave `packet_length` unencrypted and compute the mac
+    /// over the encrypted packet instead. AEAD ciphers replace the mac with their own
+    /// authentication tag.
+    ///
+    /// Packets longer than `MAX_PACKET_LENGTH` are rejected as the other side isn't required to
+    /// accept them.
+    pub fn encode_into_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), PacketError> {
+        let padding_length = self.padding_length();
+
+        // The packet length doesn't include the length field itself or the mac
+        let packet_length = 1 + self.payload.len() + padding_length;
+
+        if packet_length > MAX_PACKET_LENGTH {
+            return Err(PacketError::TooLarge { len: packet_length });
+        }
+
+        let start = buffer.len();
+        buffer.reserve(self.encoded_len());
+
+        buffer.extend((packet_length as u32).to_be_bytes());
+
+        buffer.push(padding_length as u8);
+
+        buffer.extend(&self.payload);
+
+        buffer.resize(start + 4 + packet_length, 0);
+        rng.fill_bytes(&mut buffer[start + 4 + packet_length - padding_length..]);
+
+        match self.seal(&mut buffer[start..]) {
+            Ok(mut mac) => {
+                buffer.append(&mut mac);
+                Ok(())
+            }
+            Err(e) => {
+                buffer.truncate(start);
+                Err(e)
+            }
+        }
+    }
+
+    /// Encrypt a packet in place, returning the mac or authentication tag to send after it
+    fn seal(&mut self, encoded_packet: &mut [u8]) -> Result<Vec<u8>, PacketError> {
+        match self.encryption_cipher.as_aead() {
+            Some(aead) => Ok(aead.seal(self.sequence_number, encoded_packet)),
+            None if self.mac_type.is_etm() => {
+                self.encryption_cipher.encrypt(&mut encoded_packet[4..])?;
+
+                Ok(self
+                    .mac_type
+                    .compute(&self.mac_key, self.sequence_number, encoded_packet))
+            }
+            None => {
+                let mac =
+                    self.mac_type
+                        .compute(&self.mac_key, self.sequence_number, encoded_packet);
+
+                self.encryption_cipher.encrypt(encoded_packet)?;
+
+                Ok(mac)
+            }
+        }
+    }
+}
+
+/// Reads binary packets one at a time from a stream, keeping any bytes that arrived early
+///
+/// A single read from the stream can return the end of one packet and the start of the next,
+/// or several whole packets. Those bytes are kept in a buffer until the following calls to
+/// `next_packet`, and the stream is only read again once the buffer runs out. The sequence
+/// number starts at 0 and goes up with each packet read.
+#[cfg(feature = "std")]
+pub struct PacketReader<R: std::io::Read> {
+    inner: std::io::BufReader<R>,
+    sequence_number: u32,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> PacketReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: std::io::BufReader::new(inner),
+            sequence_number: 0,
+        }
+    }
+
+    /// Read the next packet like `Packet::read_framed` and return its payload
+    ///
+    /// Returns `None` if the stream ended cleanly between two packets. A stream that ends part
+    /// way through a packet gives `PacketError::ReadError` instead.
+    ///
+    /// Besides the cipher and mac this also takes the mac key, which is needed to check the mac.
+    /// Like the cipher it is passed on every call rather than kept by the reader, because both
+    /// change after each key exchange.
+    pub fn next_packet(
+        &mut self,
+        cipher: &mut dyn Cipher,
+        mac: &Mac,
+        mac_key: &[u8],
+    ) -> Result<Option<Vec<u8>>, PacketError> {
+        use std::io::BufRead;
+
+        let buffered = self
+            .inner
+            .fill_buf()
+            .map_err(|e| PacketError::ReadError { kind: e.kind() })?;
+        if buffered.is_empty() {
+            return Ok(None);
+        }
+
+        let payload =
+            Packet::read_framed(&mut self.inner, cipher, mac, mac_key, self.sequence_number)?;
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        Ok(Some(payload))
+    }
+
+    /// The sequence number the next packet read is expected to have
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    /// Stop reading packets and get back the stream, dropping anything still buffered
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+/// Reject packets longer than `MAX_PACKET_LENGTH` before trying to read them
+#[cfg(feature = "std")]
+fn check_length(length: u32) -> Result<usize, PacketError> {
+    let length = length as usize;
+
+    match length > MAX_PACKET_LENGTH {
+        true => Err(PacketError::TooLarge { len: length }),
+        false => Ok(length),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encryption::{AesCtr, ChaCha20Poly1305, CipherError, CipherType};
+    use crate::packet::*;
+
+    #[cfg(feature = "std")]
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn packet_length_is_big_endian() {
+        let mut packet = Packet::new(vec![0; 300], Mac::None, Box::new(CipherType::None));
+
+        assert_eq!(packet.encode().unwrap()[..4], [0x00, 0x00, 0x01, 0x34]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn packet_layout() {
+        let payload = b"hello world".to_vec();
+        let mut packet = Packet::new(payload.clone(), Mac::None, Box::new(CipherType::None));
+
+        let encoded = packet
+            .encode_with_rng(&mut StdRng::seed_from_u64(0))
+            .unwrap();
+
+        let packet_length = u32::from_be_bytes(encoded[..4].try_into().unwrap()) as usize;
+        let padding_length = encoded[4] as usize;
+
+        assert_eq!(encoded.len(), 4 + packet_length);
+        assert_eq!(encoded[5..5 + packet_length - padding_length - 1], payload);
+
+        // The padding has to come from the rng so the same seed gives the same packet
+        assert_eq!(
+            encoded,
+            packet
+                .encode_with_rng(&mut StdRng::seed_from_u64(0))
+                .unwrap()
+        );
+        assert_ne!(
+            encoded,
+            packet
+                .encode_with_rng(&mut StdRng::seed_from_u64(1))
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn padding_around_block_boundaries() {
+        for (payload_length, padding_length) in [(3, 8), (4, 7), (5, 6), (11, 8), (12, 7)] {
+            let mut packet = Packet::new(
+                vec![0; payload_length],
+                Mac::None,
+                Box::new(CipherType::None),
+            );
+
+            let encoded = packet.encode().unwrap();
+
+            assert_eq!(encoded[4] as usize, padding_length);
+            assert!(padding_length >= 4);
+            assert_eq!(encoded.len() % 8, 0);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encoded_len_matches_encode() {
+        let ciphers = || -> [(Box<dyn Cipher>, Mac); 4] {
+            [
+                (Box::new(CipherType::None), Mac::None),
+                (
+                    Box::new(AesCtr::new(&[1; 16], &[2; 16]).unwrap()),
+                    Mac::HmacSha256,
+                ),
+                (
+                    Box::new(AesCtr::new(&[1; 32], &[2; 16]).unwrap()),
+                    Mac::HmacSha256Etm,
+                ),
+                (
+                    Box::new(ChaCha20Poly1305::new(&[1; 64]).unwrap()),
+                    Mac::None,
+                ),
+            ]
+        };
+
+        for payload_length in [0, 1, 7, 8, 11, 12, 16, 100, 1000] {
+            for (cipher, mac) in ciphers() {
+                let mut packet = Packet::new(vec![0; payload_length], mac, cipher);
+                let encoded_len = packet.encoded_len();
+
+                assert_eq!(packet.encode().unwrap().len(), encoded_len);
+            }
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed_round_trip() {
+        use crate::compression::Compression;
+        use crate::message::CompressionAlgorithm;
+
+        let mut compression = Compression::new(CompressionAlgorithm::Zstd).unwrap();
+        compression.set_authenticated();
+
+        let payload = b"ls -la /home/user\n".repeat(50);
+        let mut uncompressed = Packet::new(payload.clone(), Mac::None, Box::new(CipherType::None));
+        let mut packet = Packet::new(payload.clone(), Mac::None, Box::new(CipherType::None));
+
+        let encoded = packet.encode_compressed(&compression).unwrap();
+        assert!(encoded.len() < uncompressed.encode().unwrap().len());
+
+        // Encoding again starts from the original payload instead of compressing it twice
+        let again = packet.encode_compressed(&compression).unwrap();
+        assert_eq!(
+            Packet::decode_compressed(&again, &CipherType::None, &Mac::None, 0, &compression),
+            Ok((payload.clone(), again.len()))
+        );
+
+        assert_eq!(
+            Packet::decode_compressed(&encoded, &CipherType::None, &Mac::None, 0, &compression),
+            Ok((payload, encoded.len()))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encode_into_appends() {
+        let key = [1; 16];
+        let packet = |payload: &[u8]| {
+            let mut packet = Packet::new(
+                payload.to_vec(),
+                Mac::HmacSha256,
+                Box::new(AesCtr::new(&key, &[2; 16]).unwrap()),
+            );
+            packet.set_mac_key(vec![3; 32]);
+            packet
         };
 
-        encoded_packet.push(padding_length as u8);
+        let mut buffer = b"existing".to_vec();
+        packet(b"hello")
+            .encode_into_with_rng(&mut StdRng::seed_from_u64(1), &mut buffer)
+            .unwrap();
+
+        let encoded = packet(b"hello")
+            .encode_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+        assert_eq!(&buffer[..8], b"existing");
+        assert_eq!(&buffer[8..], encoded);
+
+        // A failed encode leaves the buffer alone
+        let mut buffer = b"existing".to_vec();
+        let mut packet = Packet::new(vec![0; 40000], Mac::None, Box::new(CipherType::None));
+        assert!(packet.encode_into(&mut buffer).is_err());
+
+        let mut packet = Packet::new(vec![0; 10], Mac::None, Box::new(CipherType::AES128CBC));
+        assert!(packet.encode_into(&mut buffer).is_err());
+        assert_eq!(buffer, b"existing");
+    }
+
+    #[test]
+    fn debug_redacts_payload() {
+        let mut packet = Packet::new(
+            b"secret password".to_vec(),
+            Mac::HmacSha256,
+            Box::new(AesCtr::new(&[1; 16], &[2; 16]).unwrap()),
+        );
+        packet.set_mac_key(vec![0xab; 32]);
+        packet.set_sequence_number(3);
+
+        let debug = format!("{packet:?}");
+        assert_eq!(
+            debug,
+            "Packet { payload_len: 15, mac_type: HmacSha256, cipher: AesCtr(Aes128), \
+             block_size: 16, sequence_number: 3, .. }"
+        );
+        assert!(!debug.contains("secret"));
+    }
+
+    #[cfg(feature = "std")]
+    /// A reader that hands out one byte at a time, like a slow network stream
+    struct ByteAtATime<'a>(&'a [u8]);
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for ByteAtATime<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&byte, rest)), Some(first)) => {
+                    *first = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_framed_round_trip() {
+        let ciphers = || -> [(Box<dyn Cipher>, Mac); 4] {
+            [
+                (Box::new(CipherType::None), Mac::None),
+                (
+                    Box::new(AesCtr::new(&[1; 16], &[2; 16]).unwrap()),
+                    Mac::HmacSha256,
+                ),
+                (
+                    Box::new(AesCtr::new(&[1; 32], &[2; 16]).unwrap()),
+                    Mac::HmacSha256Etm,
+                ),
+                (
+                    Box::new(ChaCha20Poly1305::new(&[1; 64]).unwrap()),
+                    Mac::None,
+                ),
+            ]
+        };
+
+        for ((cipher, mac), (mut receive_cipher, _)) in ciphers().into_iter().zip(ciphers()) {
+            let mut stream = vec![];
+            let mut cipher = Some(cipher);
+            for sequence_number in 0..2 {
+                let payload = vec![sequence_number as u8; 40];
+                let mut packet = Packet::new(payload, mac.clone(), cipher.take().unwrap());
+                packet.set_sequence_number(sequence_number);
+                packet.set_mac_key(vec![3; 32]);
+                packet.encode_into(&mut stream).unwrap();
+                cipher = Some(packet.into_cipher());
+            }
+
+            // Both packets come out of the same stream, with the cipher carrying on between them
+            let mut reader = ByteAtATime(&stream);
+            for sequence_number in 0..2 {
+                assert_eq!(
+                    Packet::read_framed(
+                        &mut reader,
+                        receive_cipher.as_mut(),
+                        &mac,
+                        &[3; 32],
+                        sequence_number
+                    ),
+                    Ok(vec![sequence_number as u8; 40])
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_framed_errors() {
+        let encode = || {
+            let mut packet = Packet::new(
+                b"hello".to_vec(),
+                Mac::HmacSha256,
+                Box::new(AesCtr::new(&[1; 16], &[2; 16]).unwrap()),
+            );
+            packet.set_mac_key(vec![3; 32]);
+            packet.encode().unwrap()
+        };
+        let read = |data: &[u8], mac_key: &[u8]| {
+            let mut cipher = AesCtr::new(&[1; 16], &[2; 16]).unwrap();
+            Packet::read_framed(
+                &mut ByteAtATime(data),
+                &mut cipher,
+                &Mac::HmacSha256,
+                mac_key,
+                0,
+            )
+        };
+
+        let encoded = encode();
+        assert_eq!(read(&encoded, &[3; 32]), Ok(b"hello".to_vec()));
+        assert_eq!(read(&encoded, &[4; 32]), Err(PacketError::InvalidMac));
+        assert_eq!(
+            read(&encoded[..encoded.len() - 1], &[3; 32]),
+            Err(PacketError::ReadError {
+                kind: std::io::ErrorKind::UnexpectedEof
+            })
+        );
+
+        let mut too_large = vec![0, 0, 0x88, 0xbc];
+        too_large.extend([0; 4]);
+        assert_eq!(
+            Packet::read_framed(
+                &mut too_large.as_slice(),
+                &mut CipherType::None,
+                &Mac::None,
+                &[],
+                0
+            ),
+            Err(PacketError::TooLarge { len: 35004 })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    /// A reader that returns everything in its first read and counts how often it is called
+    struct SingleShot {
+        data: Vec<u8>,
+        reads: usize,
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for SingleShot {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reads += 1;
 
-        encoded_packet.append(&mut self.payload.clone());
+            let len = self.data.len();
+            assert!(buf.len() >= len);
+            buf[..len].copy_from_slice(&self.data);
+            self.data.clear();
 
-        for i in 0..padding_length {
-            encoded_packet.push(i as u8);
+            Ok(len)
         }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn packet_reader_splits_buffered_packets() {
+        let mut cipher: Box<dyn Cipher> = Box::new(AesCtr::new(&[1; 16], &[2; 16]).unwrap());
+        let mut data = vec![];
+        for (sequence_number, payload) in [&b"first"[..], b"second"].into_iter().enumerate() {
+            let mut packet = Packet::new(payload.to_vec(), Mac::HmacSha256, cipher);
+            packet.set_sequence_number(sequence_number as u32);
+            packet.set_mac_key(vec![3; 32]);
+            packet.encode_into(&mut data).unwrap();
+            cipher = packet.into_cipher();
+        }
+
+        let mut reader = PacketReader::new(SingleShot { data, reads: 0 });
+        let mut cipher = AesCtr::new(&[1; 16], &[2; 16]).unwrap();
+        let mut next = || reader.next_packet(&mut cipher, &Mac::HmacSha256, &[3; 32]);
+
+        assert_eq!(next(), Ok(Some(b"first".to_vec())));
+        assert_eq!(next(), Ok(Some(b"second".to_vec())));
+        assert_eq!(next(), Ok(None));
+
+        // Both packets came from the first read, the second one only found the end of the stream
+        assert_eq!(reader.sequence_number(), 2);
+        assert_eq!(reader.into_inner().reads, 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn packet_reader_stream_ends_inside_packet() {
+        let mut packet = Packet::new(b"hello".to_vec(), Mac::None, Box::new(CipherType::None));
+        let mut data = packet.encode().unwrap();
+        data.pop();
+
+        let mut reader = PacketReader::new(data.as_slice());
+        assert_eq!(
+            reader.next_packet(&mut CipherType::None, &Mac::None, &[]),
+            Err(PacketError::ReadError {
+                kind: std::io::ErrorKind::UnexpectedEof
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn decode_round_trip() {
+        let mut packet = Packet::new(
+            b"hello world".to_vec(),
+            Mac::None,
+            Box::new(CipherType::None),
+        );
+        let mut encoded = packet.encode().unwrap();
+        let length = encoded.len();
+
+        // Anything after the packet must be left alone
+        encoded.extend(b"next");
+
+        assert_eq!(
+            Packet::decode(&encoded, &CipherType::None, &Mac::None, 0),
+            Ok((b"hello world".to_vec(), length))
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            Packet::decode(&[0, 0, 0], &CipherType::None, &Mac::None, 0),
+            Err(PacketError::UnexpectedEnd {
+                needed: 5,
+                available: 3
+            })
+        );
+        assert_eq!(
+            Packet::decode(&[0, 0, 0x88, 0xbc, 4], &CipherType::None, &Mac::None, 0),
+            Err(PacketError::TooLarge { len: 35004 })
+        );
+        assert_eq!(
+            Packet::decode(&[0, 0, 0, 12, 4, 1, 2], &CipherType::None, &Mac::None, 0),
+            Err(PacketError::UnexpectedEnd {
+                needed: 16,
+                available: 7
+            })
+        );
+
+        let mut short_padding = vec![0, 0, 0, 12, 3];
+        short_padding.extend([0; 11]);
+        assert_eq!(
+            Packet::decode(&short_padding, &CipherType::None, &Mac::None, 0),
+            Err(PacketError::InvalidPadding { padding_length: 3 })
+        );
+    }
+
+    #[test]
+    fn decode_rejects_padding_outside_packet() {
+        // A packet length of 12 leaves 11 bytes for the payload and padding
+        for padding_length in [0, 255, 11] {
+            let mut packet = vec![0, 0, 0, 12, padding_length];
+            packet.extend([0; 11]);
+
+            assert_eq!(
+                Packet::decode(&packet, &CipherType::None, &Mac::None, 0),
+                Err(PacketError::InvalidPadding {
+                    padding_length: padding_length as usize
+                }),
+                "{padding_length}"
+            );
+        }
+
+        // With encrypt-then-mac an empty packet passes the block size check
+        let mut empty = vec![0; 4];
+        empty.extend([0; 32]);
+        assert_eq!(
+            Packet::decode(&empty, &CipherType::None, &Mac::HmacSha256Etm, 0),
+            Err(PacketError::InvalidLength { len: 0 })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encode_rejects_large_packets() {
+        let mut packet = Packet::new(vec![0; 35000], Mac::None, Box::new(CipherType::None));
+
+        assert_eq!(packet.encode(), Err(PacketError::TooLarge { len: 35012 }));
+
+        let mut packet = Packet::new(vec![0; 32768], Mac::None, Box::new(CipherType::None));
+
+        assert!(packet.encode().is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encode_reports_cipher_errors() {
+        let mut packet = Packet::new(vec![0; 8], Mac::None, Box::new(CipherType::AES128CBC));
+
+        assert!(matches!(packet.encode(), Err(PacketError::Cipher(_))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn chacha20_poly1305_round_trip() {
+        let key: Vec<u8> = (0..64).collect();
+        let cipher = CipherType::ChaCha20Poly1305.instantiate(&key, &[]).unwrap();
+
+        let mut packet = Packet::new(b"hello world".to_vec(), Mac::None, cipher);
+        packet.set_sequence_number(7);
+        let encoded = packet.encode().unwrap();
+
+        // 4 byte length, 16 bytes of padded data and a 16 byte tag
+        assert_eq!(encoded.len(), 36);
+        assert_ne!(encoded[..4], [0, 0, 0, 16]);
+
+        let cipher = ChaCha20Poly1305::new(&key).unwrap();
+        assert_eq!(
+            Packet::decode(&encoded, &cipher, &Mac::None, 7),
+            Ok((b"hello world".to_vec(), 36))
+        );
+
+        // The wrong sequence number or a modified packet must fail authentication
+        assert!(Packet::decode(&encoded, &cipher, &Mac::None, 8).is_err());
+
+        let mut modified = encoded.clone();
+        modified[10] ^= 1;
+        assert_eq!(
+            Packet::decode(&modified, &cipher, &Mac::None, 7),
+            Err(PacketError::Cipher(CipherError::AuthenticationFailed))
+        );
+    }
+
+    #[test]
+    fn chacha20_poly1305_known_answer() {
+        // Generated independently from OpenSSH's PROTOCOL.chacha20poly1305 description
+        let key: Vec<u8> = (0..64).collect();
+        let cipher = ChaCha20Poly1305::new(&key).unwrap();
+
+        let encoded = [
+            0xa3, 0x9a, 0xfc, 0xba, 0x2c, 0x2e, 0x70, 0x2f, 0x22, 0xec, 0x0a, 0x29, 0x03, 0x1f,
+            0xd7, 0x94, 0x91, 0xb1, 0xc2, 0x16, 0xb0, 0x3b, 0xd7, 0x39, 0x80, 0x21, 0x81, 0x1b,
+            0x3d, 0x7f, 0xe8, 0x04, 0x8b, 0x36, 0x31, 0x39,
+        ];
+
+        assert_eq!(
+            Packet::decode(&encoded, &cipher, &Mac::None, 7),
+            Ok((b"hello world".to_vec(), 36))
+        );
+
+        let mut modified = encoded;
+        modified[35] ^= 1;
+        assert_eq!(
+            Packet::decode(&modified, &cipher, &Mac::None, 7),
+            Err(PacketError::Cipher(CipherError::AuthenticationFailed))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn unencrypted_packet_uses_minimum_padding() {
+        let mut packet = Packet::new(vec![0; 7], Mac::None, Box::new(CipherType::None));
+
+        let encoded = packet.encode().unwrap();
+
+        // The length fields and payload take 12 bytes, so 4 bytes of padding reach the next
+        // multiple of the 8 byte floor, which is also the minimum amount of padding allowed
+        assert_eq!(CipherType::None.get_block_size(), 1);
+        assert_eq!(encoded[4], 4);
+        assert_eq!(encoded.len(), 16);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encrypt_then_mac_layout() {
+        let key = [1; 16];
+        let iv = [2; 16];
+        let mac_key = [3; 32];
+
+        let mut packet = Packet::new(
+            b"hello world".to_vec(),
+            Mac::HmacSha256Etm,
+            Box::new(AesCtr::new(&key, &iv).unwrap()),
+        );
+        packet.set_mac_key(mac_key.to_vec());
+        packet.set_sequence_number(3);
+        let encoded = packet.encode().unwrap();
+
+        // The length is left in cleartext and isn't part of the block alignment
+        let packet_length = u32::from_be_bytes(encoded[..4].try_into().unwrap()) as usize;
+        assert_eq!(packet_length % 16, 0);
+        assert_eq!(encoded.len(), 4 + packet_length + 32);
+
+        // The mac covers the length and the ciphertext
+        let (packet_data, mac) = encoded.split_at(4 + packet_length);
+        assert_eq!(mac, Mac::HmacSha256Etm.compute(&mac_key, 3, packet_data));
+
+        let mut decrypted = packet_data.to_vec();
+        AesCtr::new(&key, &iv)
+            .unwrap()
+            .decrypt(&mut decrypted[4..])
+            .unwrap();
+
+        decrypted.extend(mac);
+        assert_eq!(
+            Packet::decode(&decrypted, &CipherType::None, &Mac::HmacSha256Etm, 3),
+            Ok((b"hello world".to_vec(), encoded.len()))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn mac_then_encrypt_layout() {
+        let key = [1; 16];
+        let iv = [2; 16];
+        let mac_key = [3; 32];
+
+        let mut packet = Packet::new(
+            b"hello world".to_vec(),
+            Mac::HmacSha256,
+            Box::new(AesCtr::new(&key, &iv).unwrap()),
+        );
+        packet.set_mac_key(mac_key.to_vec());
+        let encoded = packet.encode().unwrap();
+
+        // Everything but the mac is encrypted and the mac covers the plaintext
+        let (packet_data, mac) = encoded.split_at(encoded.len() - 32);
+        assert_eq!(packet_data.len() % 16, 0);
+
+        let mut decrypted = packet_data.to_vec();
+        AesCtr::new(&key, &iv)
+            .unwrap()
+            .decrypt(&mut decrypted)
+            .unwrap();
+        assert_eq!(mac, Mac::HmacSha256.compute(&mac_key, 0, &decrypted));
 
-        encoded_packet
+        decrypted.extend(mac);
+        assert_eq!(
+            Packet::decode(&decrypted, &CipherType::None, &Mac::HmacSha256, 0),
+            Ok((b"hello world".to_vec(), encoded.len()))
+        );
     }
 }
@@ -0,0 +1,70 @@
+use crate::compression::CompressionError;
+use crate::encryption::CipherError;
+use crate::mac::MacError;
+
+/// Possible errors when encoding or decoding a binary packet
+#[derive(Debug, Eq, PartialEq)]
+pub enum PacketError {
+    /// The data ended before the whole packet was available
+    ///
+    /// needed: the number of bytes required for the packet
+    /// available: the number of bytes that were actually available
+    UnexpectedEnd { needed: usize, available: usize },
+    /// The packet is larger than the maximum packet size we are willing to handle
+    ///
+    /// len: the length of the packet in bytes
+    TooLarge { len: usize },
+    /// The padding length is shorter than the required 4 bytes or leaves no room for a payload
+    InvalidPadding { padding_length: usize },
+    /// The packet length is not a multiple of the cipher block size
+    InvalidLength { len: usize },
+    /// Encrypting or decrypting the packet failed
+    Cipher(CipherError),
+    /// Compressing or decompressing the payload failed
+    Compression(CompressionError),
+    /// The mac received with the packet didn't match its contents
+    InvalidMac,
+    /// The connection was closed with a disconnect message, so no more packets can be sent
+    Closed,
+    /// Reading the packet from the underlying stream failed
+    #[cfg(feature = "std")]
+    ReadError { kind: std::io::ErrorKind },
+}
+
+impl core::fmt::Display for PacketError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedEnd { needed, available } => write!(f, "Unexpected end of packet: needed {needed} bytes but only {available} were available"),
+            Self::TooLarge { len } => write!(f, "Packet length {len} exceeds the maximum packet length"),
+            Self::InvalidPadding { padding_length } => write!(f, "Invalid padding length {padding_length}"),
+            Self::InvalidLength { len } => write!(f, "Packet length {len} is not a multiple of the cipher block size"),
+            Self::Cipher(e) => write!(f, "Cipher error: {e}"),
+            Self::Compression(e) => write!(f, "Compression error: {e}"),
+            Self::InvalidMac => write!(f, "Packet mac verification failed"),
+            Self::Closed => write!(f, "The connection has been closed"),
+            #[cfg(feature = "std")]
+            Self::ReadError { kind } => write!(f, "Failed to read packet: {kind}"),
+        }
+    }
+}
+
+impl core::error::Error for PacketError {}
+
+impl From<CipherError> for PacketError {
+    fn from(e: CipherError) -> Self {
+        Self::Cipher(e)
+    }
+}
+
+impl From<MacError> for PacketError {
+    /// Every rejected mac is reported the same way, the reason isn't useful to the other side
+    fn from(_: MacError) -> Self {
+        Self::InvalidMac
+    }
+}
+
+impl From<CompressionError> for PacketError {
+    fn from(e: CompressionError) -> Self {
+        Self::Compression(e)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::compression::CompressionError;
+use crate::encryption::CipherError;
+use crate::hostkey::SigError;
+use crate::identification::IdentificationError;
+use crate::kex::KexError;
+use crate::mac::MacError;
+use crate::message::{DecodeError, EncodeError, MessageError};
+use crate::packet::PacketError;
+#[cfg(feature = "std")]
+use crate::transport::TransportError;
+
+/// Any error from this crate, so that code using several layers can match on a single type
+#[derive(Debug, Eq, PartialEq)]
+pub enum RsshError {
+    /// An identification string couldn't be parsed, read or written
+    Identification(IdentificationError),
+    /// A message couldn't be encoded or decoded
+    Message(MessageError),
+    /// A binary packet couldn't be encoded or decoded
+    Packet(PacketError),
+    /// Encrypting or decrypting failed
+    Cipher(CipherError),
+    /// A received mac was rejected
+    Mac(MacError),
+    /// Compressing or decompressing a payload failed
+    Compression(CompressionError),
+    /// The key exchange failed
+    Kex(KexError),
+    /// A host key or signature was invalid
+    HostKey(SigError),
+    /// Running a connection failed
+    #[cfg(feature = "std")]
+    Transport(TransportError),
+}
+
+impl core::fmt::Display for RsshError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Identification(e) => write!(f, "Identification error: {e}"),
+            Self::Message(e) => write!(f, "Message error: {e}"),
+            Self::Packet(e) => write!(f, "Packet error: {e}"),
+            Self::Cipher(e) => write!(f, "Cipher error: {e}"),
+            Self::Mac(e) => write!(f, "Mac error: {e}"),
+            Self::Compression(e) => write!(f, "Compression error: {e}"),
+            Self::Kex(e) => write!(f, "Key exchange error: {e}"),
+            Self::HostKey(e) => write!(f, "Host key error: {e}"),
+            #[cfg(feature = "std")]
+            Self::Transport(e) => write!(f, "Transport error: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for RsshError {}
+
+impl From<IdentificationError> for RsshError {
+    fn from(e: IdentificationError) -> Self {
+        Self::Identification(e)
+    }
+}
+
+impl From<MessageError> for RsshError {
+    fn from(e: MessageError) -> Self {
+        Self::Message(e)
+    }
+}
+
+impl From<DecodeError> for RsshError {
+    fn from(e: DecodeError) -> Self {
+        Self::Message(e.into())
+    }
+}
+
+impl From<EncodeError> for RsshError {
+    fn from(e: EncodeError) -> Self {
+        Self::Message(e.into())
+    }
+}
+
+impl From<PacketError> for RsshError {
+    fn from(e: PacketError) -> Self {
+        Self::Packet(e)
+    }
+}
+
+impl From<CipherError> for RsshError {
+    fn from(e: CipherError) -> Self {
+        Self::Cipher(e)
+    }
+}
+
+impl From<MacError> for RsshError {
+    fn from(e: MacError) -> Self {
+        Self::Mac(e)
+    }
+}
+
+impl From<CompressionError> for RsshError {
+    fn from(e: CompressionError) -> Self {
+        Self::Compression(e)
+    }
+}
+
+impl From<KexError> for RsshError {
+    fn from(e: KexError) -> Self {
+        Self::Kex(e)
+    }
+}
+
+impl From<SigError> for RsshError {
+    fn from(e: SigError) -> Self {
+        Self::HostKey(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<TransportError> for RsshError {
+    fn from(e: TransportError) -> Self {
+        Self::Transport(e)
+    }
+}
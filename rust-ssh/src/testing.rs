@@ -0,0 +1,168 @@
+//! Helpers for testing code built on this crate without real sockets
+//!
+//! This module is only available with the `test-util` feature.
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// The bytes written in one direction of a loopback connection
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<PipeState>,
+    readable: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    data: VecDeque<u8>,
+    /// The number of streams that can still write to the pipe, once this reaches 0 reading
+    /// returns end of file
+    writers: usize,
+}
+
+impl Pipe {
+    fn new() -> Arc<Self> {
+        let pipe = Self::default();
+        pipe.state.lock().unwrap().writers = 1;
+
+        Arc::new(pipe)
+    }
+}
+
+/// One end of a `LoopbackPair`
+///
+/// Reads block until the other end writes something, and return end of file once every clone
+/// of the other end has been dropped. Cloning gives another handle to the same end, which is
+/// useful for reading and writing from different places like `TcpStream::try_clone`.
+pub struct LoopbackStream {
+    incoming: Arc<Pipe>,
+    outgoing: Arc<Pipe>,
+}
+
+impl Clone for LoopbackStream {
+    fn clone(&self) -> Self {
+        self.outgoing.state.lock().unwrap().writers += 1;
+
+        Self {
+            incoming: self.incoming.clone(),
+            outgoing: self.outgoing.clone(),
+        }
+    }
+}
+
+impl Drop for LoopbackStream {
+    fn drop(&mut self) {
+        // The lock is only poisoned if another thread panicked, which is already a failed test
+        if let Ok(mut state) = self.outgoing.state.lock() {
+            state.writers -= 1;
+        }
+        self.outgoing.readable.notify_all();
+    }
+}
+
+impl Read for LoopbackStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut state = self.incoming.state.lock().unwrap();
+        while state.data.is_empty() && state.writers > 0 && !buf.is_empty() {
+            state = self.incoming.readable.wait(state).unwrap();
+        }
+
+        let len = buf.len().min(state.data.len());
+        for (byte, value) in buf.iter_mut().zip(state.data.drain(..len)) {
+            *byte = value;
+        }
+
+        Ok(len)
+    }
+}
+
+impl Write for LoopbackStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.outgoing.state.lock().unwrap().data.extend(buf);
+        self.outgoing.readable.notify_all();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Two connected in-memory streams, what is written to one can be read from the other
+///
+/// Each side of a connection, such as a client and a server transport, can be run on its own
+/// thread with one of the streams.
+pub struct LoopbackPair {
+    pub client: LoopbackStream,
+    pub server: LoopbackStream,
+}
+
+impl LoopbackPair {
+    pub fn new() -> Self {
+        let to_server = Pipe::new();
+        let to_client = Pipe::new();
+
+        Self {
+            client: LoopbackStream {
+                incoming: to_client.clone(),
+                outgoing: to_server.clone(),
+            },
+            server: LoopbackStream {
+                incoming: to_server,
+                outgoing: to_client,
+            },
+        }
+    }
+}
+
+impl Default for LoopbackPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::*;
+
+    #[test]
+    fn loopback_streams_are_connected() {
+        let LoopbackPair {
+            mut client,
+            mut server,
+        } = LoopbackPair::new();
+
+        client.write_all(b"hello").unwrap();
+        server.write_all(b"world").unwrap();
+
+        let mut buffer = [0; 5];
+        server.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"hello");
+        client.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"world");
+    }
+
+    #[test]
+    fn loopback_reads_wait_for_the_other_end() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+
+        let reader = std::thread::spawn(move || {
+            let mut received = vec![];
+            let mut server = server;
+            server.read_to_end(&mut received).unwrap();
+            received
+        });
+
+        // A clone keeps the end open, so end of file only comes after both are dropped
+        let mut writer = client.clone();
+        writer.write_all(b"one ").unwrap();
+        drop(writer);
+        let mut client = client;
+        client.write_all(b"two").unwrap();
+        drop(client);
+
+        assert_eq!(reader.join().unwrap(), b"one two");
+    }
+}
@@ -0,0 +1,1212 @@
+//! The client side of the SSH transport layer protocol from RFC 4253
+
+mod transport_error;
+
+pub use transport_error::*;
+
+use crate::compression::Compression;
+use crate::encryption::{Cipher, CipherType};
+use crate::hostkey::Ed25519HostKey;
+use crate::identification::Identification;
+use crate::kex::ClientKex;
+use crate::mac::Mac;
+use crate::message::additional::{IgnoreMessage, UnimplementedMessage};
+use crate::message::disconnect::{DisconnectMessage, DisconnectMessageType};
+use crate::message::message_type::MessageType;
+use crate::message::*;
+use crate::packet::{Packet, PacketError};
+use crate::RsshError;
+
+use std::collections::VecDeque;
+use std::io::{BufReader, Read, Write};
+use zeroize::Zeroizing;
+
+/// The encryption and integrity state for one direction of a connection
+struct DirectionState {
+    cipher: Box<dyn Cipher>,
+    mac: Mac,
+    mac_key: Zeroizing<Vec<u8>>,
+    compression: Compression,
+    /// The number of packets sent or received in this direction, wrapping at 2^32 as described in
+    /// RFC 4253 section 6.4. It is never reset, even by a new key exchange.
+    sequence_number: u32,
+    /// The number of bytes sent or received in this direction since the last key exchange
+    bytes_since_kex: u64,
+    /// The number of packets sent or received in this direction since the last key exchange
+    packets_since_kex: u64,
+}
+
+impl DirectionState {
+    /// The state at the start of a connection, before any keys have been exchanged
+    fn new() -> Self {
+        Self {
+            cipher: Box::new(CipherType::None),
+            mac: Mac::None,
+            mac_key: Zeroizing::new(vec![]),
+            compression: Compression::none(),
+            sequence_number: 0,
+            bytes_since_kex: 0,
+            packets_since_kex: 0,
+        }
+    }
+
+    /// Start using newly exchanged keys, the sequence number carries on unchanged
+    fn set_keys(&mut self, cipher: Box<dyn Cipher>, mac: Mac, mac_key: Vec<u8>) {
+        self.cipher = cipher;
+        self.mac = mac;
+        self.mac_key = Zeroizing::new(mac_key);
+        self.bytes_since_kex = 0;
+        self.packets_since_kex = 0;
+    }
+
+    /// Count a packet of `len` bytes towards the rekey thresholds
+    fn count_packet(&mut self, len: usize) {
+        self.bytes_since_kex += len as u64;
+        self.packets_since_kex += 1;
+    }
+
+    /// Encode `payload` as a binary packet and write it to `writer`
+    fn write_packet<W: Write>(
+        &mut self,
+        writer: &mut W,
+        payload: &[u8],
+    ) -> Result<(), TransportError> {
+        // The packet takes ownership of the cipher, so it is swapped out while encoding
+        let cipher = std::mem::replace(&mut self.cipher, Box::new(CipherType::None));
+
+        let mut packet = Packet::new(payload.to_vec(), self.mac.clone(), cipher);
+        packet.set_sequence_number(self.sequence_number);
+        packet.set_mac_key(self.mac_key.to_vec());
+
+        let encoded = packet.encode_compressed(&self.compression);
+        self.cipher = packet.into_cipher();
+        let encoded = encoded?;
+
+        writer.write_all(&encoded)?;
+        writer.flush()?;
+
+        self.count_packet(encoded.len());
+
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        Ok(())
+    }
+
+    /// Read exactly one binary packet from `reader`, returning its payload
+    fn read_packet<R: Read>(&mut self, reader: &mut R) -> Result<Vec<u8>, TransportError> {
+        let sequence_number = self.sequence_number;
+
+        let data = Packet::read_decrypted(
+            reader,
+            self.cipher.as_mut(),
+            &self.mac,
+            &self.mac_key,
+            sequence_number,
+        )?;
+
+        let (payload, _) = Packet::decode_compressed(
+            &data,
+            self.cipher.as_ref(),
+            &self.mac,
+            sequence_number,
+            &self.compression,
+        )?;
+
+        self.count_packet(data.len());
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        Ok(payload)
+    }
+}
+
+/// When to run a new key exchange on a long-lived connection
+///
+/// RFC 4344 recommends rekeying after about a gigabyte of data, and well before 2^32 packets so
+/// that sequence numbers never repeat under the same keys. Either limit being reached in either
+/// direction makes a rekey due.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RekeyPolicy {
+    /// The number of bytes sent or received since the last key exchange
+    pub max_bytes: u64,
+    /// The number of packets sent or received since the last key exchange
+    pub max_packets: u64,
+}
+
+impl Default for RekeyPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: 1 << 30,
+            max_packets: 1 << 31,
+        }
+    }
+}
+
+impl RekeyPolicy {
+    /// Check whether one direction has gone past either limit
+    fn exceeded_by(&self, state: &DirectionState) -> bool {
+        state.bytes_since_kex >= self.max_bytes || state.packets_since_kex >= self.max_packets
+    }
+}
+
+/// Message numbers used by algorithm negotiation and the key exchange methods (RFC 4250 section
+/// 4.1.2)
+///
+/// Any other message, such as a service accept or `SSH_MSG_EXT_INFO`, may already be in flight
+/// when a key exchange starts and is kept until it is over.
+const KEX_MESSAGES: core::ops::RangeInclusive<u8> = 20..=49;
+
+/// The most random bytes put in each `SSH_MSG_IGNORE` packet when they are being injected
+const MAX_IGNORE_PAYLOAD: usize = 64;
+
+/// The key and iv lengths of a negotiated cipher, if we can use it
+fn key_lengths_for(cipher: &CipherType) -> Option<(usize, usize)> {
+    match cipher {
+        CipherType::ChaCha20Poly1305 => Some((64, 0)),
+        CipherType::AES256CTR => Some((32, 16)),
+        CipherType::AES192CTR => Some((24, 16)),
+        CipherType::AES128CTR => Some((16, 16)),
+        _ => None,
+    }
+}
+
+/// Negotiate one kind of algorithm, returning an error when there is nothing in common
+fn negotiate_or_fail<T>(
+    kind: &str,
+    client: &NameList<T>,
+    server: &NameList<T>,
+) -> Result<T, TransportError>
+where
+    T: std::fmt::Display + PartialEq + Clone,
+{
+    negotiate(client, server).ok_or_else(|| TransportError::NoCommonAlgorithm { kind: kind.into() })
+}
+
+/// The algorithms for one direction of the connection
+struct DirectionAlgorithms {
+    cipher: CipherType,
+    key_len: usize,
+    iv_len: usize,
+    mac: Mac,
+    mac_key_len: usize,
+}
+
+impl DirectionAlgorithms {
+    fn negotiate(
+        encryption_client: &NameList<CipherType>,
+        encryption_server: &NameList<CipherType>,
+        mac_client: &NameList<Mac>,
+        mac_server: &NameList<Mac>,
+    ) -> Result<Self, TransportError> {
+        let cipher = negotiate_or_fail("encryption", encryption_client, encryption_server)?;
+        let (key_len, iv_len) =
+            key_lengths_for(&cipher).ok_or_else(|| TransportError::NoCommonAlgorithm {
+                kind: "encryption".into(),
+            })?;
+
+        // AEAD ciphers authenticate packets themselves, so the negotiated mac isn't used
+        let (mac, mac_key_len) = match cipher {
+            CipherType::ChaCha20Poly1305 => (Mac::None, 0),
+            _ => {
+                let mac = negotiate_or_fail("mac", mac_client, mac_server)?;
+                let key_len = mac
+                    .key_len()
+                    .ok_or_else(|| TransportError::NoCommonAlgorithm { kind: "mac".into() })?;
+                (mac, key_len)
+            }
+        };
+
+        Ok(Self {
+            cipher,
+            key_len,
+            iv_len,
+            mac,
+            mac_key_len,
+        })
+    }
+}
+
+/// A client connection to an SSH server over any byte stream
+///
+/// `connect` runs the whole handshake: the identification exchange, algorithm negotiation, a
+/// `curve25519-sha256` key exchange and verification of the server's `ssh-ed25519` host key.
+/// Afterwards packets are sent and received encrypted with `send_packet` and `recv_packet`.
+///
+/// The host key signature is checked, but whether the key belongs to the server being connected
+/// to is left to the caller through `host_key`.
+///
+/// Keys are exchanged again whenever the `RekeyPolicy` says so, or when the server starts a new
+/// key exchange. Connection protocol messages that arrive in the middle of one are kept and
+/// returned by `recv_packet` afterwards.
+pub struct ClientTransport<S: Read + Write> {
+    stream: BufReader<S>,
+    identification: Identification,
+    server_identification: Identification,
+    session_id: Vec<u8>,
+    host_key: Vec<u8>,
+    send: DirectionState,
+    recv: DirectionState,
+    rekey_policy: RekeyPolicy,
+    /// Messages received during a key exchange, waiting to be returned by `recv_packet`
+    pending: VecDeque<Vec<u8>>,
+    /// Whether user authentication has succeeded, which starts delayed compression
+    authenticated: bool,
+    /// Whether an `SSH_MSG_IGNORE` packet with a random payload is sent before each packet
+    inject_ignore_packets: bool,
+    /// Whether `disconnect` has been called, after which nothing more can be sent or received
+    closed: bool,
+}
+
+impl<S: Read + Write> ClientTransport<S> {
+    /// Connect using the default identification
+    pub fn connect(stream: S) -> Result<Self, TransportError> {
+        Self::connect_with(stream, Identification::default_ident())
+    }
+
+    /// Connect, sending `identification` to the server
+    pub fn connect_with(stream: S, identification: Identification) -> Result<Self, TransportError> {
+        let mut stream = BufReader::new(stream);
+
+        stream
+            .get_mut()
+            .write_all(identification.try_encode_to_string()?.as_bytes())?;
+        stream.get_mut().flush()?;
+
+        let (_, server_identification) = Identification::read_from(&mut stream)?;
+
+        let mut transport = Self {
+            stream,
+            identification,
+            server_identification,
+            session_id: vec![],
+            host_key: vec![],
+            send: DirectionState::new(),
+            recv: DirectionState::new(),
+            rekey_policy: RekeyPolicy::default(),
+            pending: VecDeque::new(),
+            authenticated: false,
+            inject_ignore_packets: false,
+            closed: false,
+        };
+
+        transport.exchange_keys(None)?;
+
+        Ok(transport)
+    }
+
+    /// Run a key exchange and start using the new keys
+    ///
+    /// `server_kex_init_payload` is the server's `KexInit` when it started the exchange.
+    fn exchange_keys(
+        &mut self,
+        server_kex_init_payload: Option<Vec<u8>>,
+    ) -> Result<(), TransportError> {
+        let mut client_kex_init = KexInitMessage::default_client();
+        if !self.session_id.is_empty() {
+            client_kex_init = client_kex_init.without_kex_extensions();
+        }
+        let client_kex_init_payload = client_kex_init.encode()?;
+        self.send
+            .write_packet(self.stream.get_mut(), &client_kex_init_payload)?;
+
+        let server_kex_init_payload = match server_kex_init_payload {
+            Some(payload) => payload,
+            None => self.read_kex_packet()?,
+        };
+        let server_kex_init = KexInitMessage::decode(&server_kex_init_payload)?;
+
+        let kex = negotiate_kex(
+            &client_kex_init.kex_algorithms,
+            &server_kex_init.kex_algorithms,
+        )
+        .and_then(|algorithm| ClientKex::new(&algorithm))
+        .ok_or_else(|| TransportError::NoCommonAlgorithm {
+            kind: "key exchange".into(),
+        })?;
+        negotiate_or_fail(
+            "host key",
+            &client_kex_init.server_host_key_algorithms,
+            &server_kex_init.server_host_key_algorithms,
+        )?;
+        let compression_client_to_server = negotiate_or_fail(
+            "compression",
+            &client_kex_init.compression_algorithms_client_to_server,
+            &server_kex_init.compression_algorithms_client_to_server,
+        )?;
+        let compression_server_to_client = negotiate_or_fail(
+            "compression",
+            &client_kex_init.compression_algorithms_server_to_client,
+            &server_kex_init.compression_algorithms_server_to_client,
+        )?;
+
+        let client_to_server = DirectionAlgorithms::negotiate(
+            &client_kex_init.encryption_algorithms_client_to_server,
+            &server_kex_init.encryption_algorithms_client_to_server,
+            &client_kex_init.mac_algorithms_client_to_server,
+            &server_kex_init.mac_algorithms_client_to_server,
+        )?;
+        let server_to_client = DirectionAlgorithms::negotiate(
+            &client_kex_init.encryption_algorithms_server_to_client,
+            &server_kex_init.encryption_algorithms_server_to_client,
+            &client_kex_init.mac_algorithms_server_to_client,
+            &server_kex_init.mac_algorithms_server_to_client,
+        )?;
+
+        self.send
+            .write_packet(self.stream.get_mut(), &kex.init_payload())?;
+
+        let reply = self.read_kex_packet()?;
+        let output = kex.process_reply(
+            &self.identification.to_string(),
+            &self.server_identification.to_string(),
+            &client_kex_init_payload,
+            &server_kex_init_payload,
+            &reply,
+        )?;
+
+        Ed25519HostKey::from_blob(&output.host_key)?
+            .verify(&output.exchange_hash, &output.signature)?;
+
+        // The exchange hash of the first key exchange stays the session id for the connection
+        if self.session_id.is_empty() {
+            self.session_id = output.exchange_hash.clone();
+        }
+        self.host_key = output.host_key.clone();
+
+        self.send
+            .write_packet(self.stream.get_mut(), &NewKeys.encode()?)?;
+        NewKeys::decode(&self.read_kex_packet()?)?;
+
+        let key_len = [&client_to_server, &server_to_client]
+            .iter()
+            .map(|x| x.key_len.max(x.iv_len).max(x.mac_key_len))
+            .max()
+            .unwrap_or(0);
+        let [iv_c, iv_s, key_c, key_s, mac_c, mac_s] =
+            output.derive_keys(&self.session_id, key_len);
+
+        let install = |algorithms: &DirectionAlgorithms,
+                       iv: &[u8],
+                       key: &[u8],
+                       mac_key: &[u8]|
+         -> Result<_, TransportError> {
+            let cipher = algorithms
+                .cipher
+                .instantiate(&key[..algorithms.key_len], &iv[..algorithms.iv_len])?;
+
+            Ok((
+                cipher,
+                algorithms.mac.clone(),
+                mac_key[..algorithms.mac_key_len].to_vec(),
+            ))
+        };
+
+        let (cipher, mac, mac_key) = install(&client_to_server, &iv_c, &key_c, &mac_c)?;
+        self.send.set_keys(cipher, mac, mac_key);
+
+        let (cipher, mac, mac_key) = install(&server_to_client, &iv_s, &key_s, &mac_s)?;
+        self.recv.set_keys(cipher, mac, mac_key);
+
+        // Delayed compression stays on after a rekey once it has started
+        let authenticated = self.authenticated;
+        let compression = |algorithm| -> Result<_, TransportError> {
+            let mut compression = Compression::new(algorithm).map_err(PacketError::from)?;
+            if authenticated {
+                compression.set_authenticated();
+            }
+
+            Ok(compression)
+        };
+        self.send.compression = compression(compression_client_to_server)?;
+        self.recv.compression = compression(compression_server_to_client)?;
+
+        Ok(())
+    }
+
+    /// Read the next key exchange packet, keeping any other messages the server sent before it
+    /// noticed the key exchange for later
+    ///
+    /// Ignore and debug messages are skipped, a disconnect or unimplemented message from the
+    /// server ends the key exchange with an error.
+    fn read_kex_packet(&mut self) -> Result<Vec<u8>, TransportError> {
+        loop {
+            let payload = self.recv.read_packet(&mut self.stream)?;
+
+            match payload.first() {
+                Some(&message_type)
+                    if message_type == MessageType::Ignore as u8
+                        || message_type == MessageType::Debug as u8 => {}
+                Some(&message_type) if message_type == MessageType::Disconnect as u8 => {
+                    let disconnect = DisconnectMessage::decode(&payload)?;
+                    self.closed = true;
+
+                    return Err(TransportError::Disconnected {
+                        reason: disconnect.reason,
+                        description: disconnect.description,
+                    });
+                }
+                Some(&message_type) if message_type == MessageType::Unimplemented as u8 => {
+                    let unimplemented = UnimplementedMessage::decode(&payload)?;
+
+                    return Err(TransportError::Unimplemented {
+                        sequence_number: unimplemented.sequence_number,
+                    });
+                }
+                Some(message_type) if KEX_MESSAGES.contains(message_type) => return Ok(payload),
+                _ => self.pending.push_back(payload),
+            }
+        }
+    }
+
+    /// Send a packet with the given payload, exchanging keys first if a rekey is due
+    pub fn send_packet(&mut self, payload: &[u8]) -> Result<(), TransportError> {
+        if self.closed {
+            return Err(PacketError::Closed.into());
+        }
+
+        if self.rekey_due() {
+            self.rekey()?;
+        }
+
+        if self.inject_ignore_packets {
+            let ignore = IgnoreMessage::with_random_payload(MAX_IGNORE_PAYLOAD);
+            self.send
+                .write_packet(self.stream.get_mut(), &ignore.encode()?)?;
+        }
+
+        self.send.write_packet(self.stream.get_mut(), payload)
+    }
+
+    /// Wait for the next packet and return its payload
+    ///
+    /// Key exchanges started by the server are handled here rather than returned, and
+    /// `SSH_MSG_IGNORE` packets are dropped.
+    pub fn recv_packet(&mut self) -> Result<Vec<u8>, TransportError> {
+        if self.closed {
+            return Err(PacketError::Closed.into());
+        }
+
+        if let Some(payload) = self.pending.pop_front() {
+            return Ok(payload);
+        }
+
+        loop {
+            let payload = self.recv.read_packet(&mut self.stream)?;
+
+            match payload.first() {
+                Some(&message_type) if message_type == MessageType::Ignore as u8 => {}
+                Some(&message_type) if message_type == MessageType::KexInit as u8 => {
+                    self.exchange_keys(Some(payload))?;
+
+                    if let Some(payload) = self.pending.pop_front() {
+                        return Ok(payload);
+                    }
+                }
+                _ => return Ok(payload),
+            }
+        }
+    }
+
+    /// Iterate over the messages received, decoded with `decode_any_message`
+    ///
+    /// The iterator ends after a `DisconnectMessage` or after an error from `recv_packet`. A
+    /// message that can't be decoded is returned as an error without ending it.
+    pub fn messages(&mut self) -> MessageStream<'_, S> {
+        MessageStream {
+            transport: self,
+            finished: false,
+        }
+    }
+
+    /// Close the connection by sending an `SSH_MSG_DISCONNECT` with the given reason
+    ///
+    /// The message is encrypted with the current keys like any other packet. Afterwards every
+    /// send or receive fails with `PacketError::Closed`, and the stream is closed once the
+    /// transport is dropped.
+    pub fn disconnect(
+        &mut self,
+        reason: DisconnectMessageType,
+        description: &str,
+    ) -> Result<(), TransportError> {
+        if self.closed {
+            return Err(PacketError::Closed.into());
+        }
+        self.closed = true;
+
+        let message = DisconnectMessage {
+            reason,
+            description: description.into(),
+            language_tag: "".into(),
+        };
+        self.send
+            .write_packet(self.stream.get_mut(), &message.encode()?)
+    }
+
+    /// Whether enough data has gone through either direction that new keys should be exchanged
+    pub fn rekey_due(&self) -> bool {
+        self.rekey_policy.exceeded_by(&self.send) || self.rekey_policy.exceeded_by(&self.recv)
+    }
+
+    /// Start a new key exchange now, regardless of the rekey policy
+    pub fn rekey(&mut self) -> Result<(), TransportError> {
+        self.exchange_keys(None)
+    }
+
+    /// Record that user authentication has succeeded
+    ///
+    /// Delayed compression algorithms such as `zstd@openssh.com` only start compressing after
+    /// this, in both directions.
+    pub fn set_authenticated(&mut self) {
+        self.authenticated = true;
+        self.send.compression.set_authenticated();
+        self.recv.compression.set_authenticated();
+    }
+
+    /// Send an `SSH_MSG_IGNORE` packet with a random payload before every packet, to resist
+    /// traffic analysis at the cost of some bandwidth
+    pub fn set_inject_ignore_packets(&mut self, inject_ignore_packets: bool) {
+        self.inject_ignore_packets = inject_ignore_packets;
+    }
+
+    /// Change when keys are exchanged again
+    pub fn set_rekey_policy(&mut self, policy: RekeyPolicy) {
+        self.rekey_policy = policy;
+    }
+
+    /// The identification the server sent
+    pub fn server_identification(&self) -> &Identification {
+        &self.server_identification
+    }
+
+    /// The session id, the exchange hash of the first key exchange
+    pub fn session_id(&self) -> &[u8] {
+        &self.session_id
+    }
+
+    /// The sequence number the next packet sent will use
+    pub fn send_sequence_number(&self) -> u32 {
+        self.send.sequence_number
+    }
+
+    /// The sequence number the next packet received is expected to have
+    pub fn recv_sequence_number(&self) -> u32 {
+        self.recv.sequence_number
+    }
+
+    /// The server's public host key blob, which should be checked against the known hosts
+    pub fn host_key(&self) -> &[u8] {
+        &self.host_key
+    }
+}
+
+/// The messages received by a `ClientTransport`, see `ClientTransport::messages`
+pub struct MessageStream<'a, S: Read + Write> {
+    transport: &'a mut ClientTransport<S>,
+    finished: bool,
+}
+
+impl<S: Read + Write> Iterator for MessageStream<'_, S> {
+    type Item = Result<Box<dyn Message>, RsshError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let payload = match self.transport.recv_packet() {
+            Ok(payload) => payload,
+            Err(e) => {
+                self.finished = true;
+                return Some(Err(e.into()));
+            }
+        };
+
+        // Nothing more is sent after a disconnect message
+        if payload.first() == Some(&(MessageType::Disconnect as u8)) {
+            self.finished = true;
+        }
+
+        Some(decode_any_message(&payload).map_err(RsshError::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::Writer;
+    use crate::kex::*;
+    use crate::message::additional::DebugMessage;
+    use crate::message::ecdh::{KexEcdhInit, KexEcdhReply};
+    use crate::message::ext_info::{ExtInfo, Extension};
+    use crate::testing::{LoopbackPair, LoopbackStream};
+    use crate::transport::*;
+
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::io::BufRead;
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    fn blob(algorithm: &str, data: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.put_string(algorithm.as_bytes());
+        writer.put_string(data);
+        writer.into_bytes()
+    }
+
+    /// The server side of one connection in the tests
+    struct TestServer {
+        reader: BufReader<LoopbackStream>,
+        writer: LoopbackStream,
+        client_ident: String,
+        encryption: CipherType,
+        mac: Mac,
+        session_id: Vec<u8>,
+        send: DirectionState,
+        recv: DirectionState,
+    }
+
+    impl TestServer {
+        /// Run a key exchange, passing the client's `KexInit` if it has already been received
+        fn exchange_keys(
+            &mut self,
+            client_kex_init_payload: Option<Vec<u8>>,
+        ) -> Result<(), TransportError> {
+            let mut server_kex_init = KexInitMessage::default_server();
+            server_kex_init.encryption_algorithms_client_to_server =
+                vec![self.encryption.clone()].into();
+            server_kex_init.encryption_algorithms_server_to_client =
+                vec![self.encryption.clone()].into();
+            server_kex_init.mac_algorithms_client_to_server = vec![self.mac.clone()].into();
+            server_kex_init.mac_algorithms_server_to_client = vec![self.mac.clone()].into();
+            let server_kex_init = server_kex_init.encode()?;
+            self.send.write_packet(&mut self.writer, &server_kex_init)?;
+
+            let client_kex_init_payload = match client_kex_init_payload {
+                Some(payload) => payload,
+                None => self.recv.read_packet(&mut self.reader)?,
+            };
+
+            let init = KexEcdhInit::decode(&self.recv.read_packet(&mut self.reader)?)?;
+            let client_public: [u8; 32] = init.client_public.try_into().unwrap();
+
+            let secret = StaticSecret::from([7; 32]);
+            let server_public = PublicKey::from(&secret);
+            let shared_secret = secret
+                .diffie_hellman(&PublicKey::from(client_public))
+                .as_bytes()
+                .to_vec();
+
+            let signing_key = SigningKey::from_bytes(&[9; 32]);
+            let host_key = blob("ssh-ed25519", signing_key.verifying_key().as_bytes());
+
+            let exchange_hash = compute_ecdh_exchange_hash(
+                HashAlg::Sha256,
+                &self.client_ident,
+                SERVER_IDENT,
+                &client_kex_init_payload,
+                &server_kex_init,
+                &host_key,
+                &client_public,
+                server_public.as_bytes(),
+                &shared_secret,
+            );
+            let signature = blob("ssh-ed25519", &signing_key.sign(&exchange_hash).to_bytes());
+
+            let reply = KexEcdhReply {
+                host_key: host_key.clone(),
+                server_public: server_public.as_bytes().to_vec(),
+                signature: signature.clone(),
+            };
+            self.send.write_packet(&mut self.writer, &reply.encode()?)?;
+
+            self.send
+                .write_packet(&mut self.writer, &NewKeys.encode()?)?;
+            NewKeys::decode(&self.recv.read_packet(&mut self.reader)?)?;
+
+            if self.session_id.is_empty() {
+                self.session_id = exchange_hash.clone();
+            }
+
+            let output = KexOutput {
+                hash: HashAlg::Sha256,
+                shared_secret: shared_secret.into(),
+                exchange_hash,
+                host_key,
+                signature,
+            };
+            let [iv_c, iv_s, key_c, key_s, mac_c, mac_s] = output.derive_keys(&self.session_id, 64);
+
+            let algorithms = DirectionAlgorithms::negotiate(
+                &vec![self.encryption.clone()].into(),
+                &vec![self.encryption.clone()].into(),
+                &vec![self.mac.clone()].into(),
+                &vec![self.mac.clone()].into(),
+            )?;
+            let cipher = |iv: &[u8], key: &[u8]| {
+                algorithms
+                    .cipher
+                    .instantiate(&key[..algorithms.key_len], &iv[..algorithms.iv_len])
+            };
+
+            self.recv.set_keys(
+                cipher(&iv_c, &key_c)?,
+                algorithms.mac.clone(),
+                mac_c[..algorithms.mac_key_len].to_vec(),
+            );
+            self.send.set_keys(
+                cipher(&iv_s, &key_s)?,
+                algorithms.mac,
+                mac_s[..algorithms.mac_key_len].to_vec(),
+            );
+
+            Ok(())
+        }
+    }
+
+    const SERVER_IDENT: &str = "SSH-2.0-test_server";
+
+    /// A minimal server that offers only the given algorithms, runs a key exchange and then
+    /// echoes back every packet it receives until the client goes away
+    ///
+    /// Each `KexInit` from the client starts a new key exchange. When `rekey_after` is set the
+    /// server also starts one itself after echoing that many packets, and then sends `rekeyed`.
+    /// A disconnect message from the client is echoed back, then ends the connection and is
+    /// returned.
+    fn run_server(
+        stream: LoopbackStream,
+        encryption: CipherType,
+        mac: Mac,
+        rekey_after: Option<usize>,
+    ) -> Result<Option<DisconnectMessage>, TransportError> {
+        let mut reader = BufReader::new(stream.clone());
+        let mut writer = stream;
+
+        writer.write_all(format!("{SERVER_IDENT}\r\n").as_bytes())?;
+
+        let mut client_ident = String::new();
+        reader.read_line(&mut client_ident)?;
+
+        let mut server = TestServer {
+            reader,
+            writer,
+            client_ident: client_ident.trim_end().into(),
+            encryption,
+            mac,
+            session_id: vec![],
+            send: DirectionState::new(),
+            recv: DirectionState::new(),
+        };
+
+        server.exchange_keys(None)?;
+
+        let mut echoed = 0;
+        loop {
+            let payload = match server.recv.read_packet(&mut server.reader) {
+                Ok(payload) => payload,
+                Err(TransportError::Io { .. }) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+
+            if payload[0] == MessageType::Disconnect as u8 {
+                server.send.write_packet(&mut server.writer, &payload)?;
+                return Ok(Some(DisconnectMessage::decode(&payload)?));
+            }
+
+            if payload[0] == MessageType::KexInit as u8 {
+                server.exchange_keys(Some(payload))?;
+                continue;
+            }
+
+            server.send.write_packet(&mut server.writer, &payload)?;
+            echoed += 1;
+
+            if Some(echoed) == rekey_after {
+                server.exchange_keys(None)?;
+                server.send.write_packet(&mut server.writer, b"rekeyed")?;
+            }
+        }
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        let mut send = DirectionState::new();
+        let mut recv = DirectionState::new();
+        for state in [&mut send, &mut recv] {
+            state.set_keys(Box::new(CipherType::None), Mac::HmacSha256, vec![1; 32]);
+            state.sequence_number = u32::MAX;
+        }
+
+        let mut data = vec![];
+        send.write_packet(&mut data, b"first").unwrap();
+        send.write_packet(&mut data, b"second").unwrap();
+        assert_eq!(send.sequence_number, 1);
+
+        // Receiving doesn't depend on the send counter, but the macs only match if both sides
+        // wrapped the same way
+        assert_eq!(recv.sequence_number, u32::MAX);
+        let mut reader = &data[..];
+        assert_eq!(recv.read_packet(&mut reader).unwrap(), b"first");
+        assert_eq!(recv.sequence_number, 0);
+        assert_eq!(recv.read_packet(&mut reader).unwrap(), b"second");
+        assert_eq!(recv.sequence_number, 1);
+    }
+
+    #[test]
+    fn mac_uses_sequence_number() {
+        let mut send = DirectionState::new();
+        let mut recv = DirectionState::new();
+        send.set_keys(Box::new(CipherType::None), Mac::HmacSha256, vec![1; 32]);
+        recv.set_keys(Box::new(CipherType::None), Mac::HmacSha256, vec![1; 32]);
+        recv.sequence_number = 5;
+
+        let mut data = vec![];
+        send.write_packet(&mut data, b"hello").unwrap();
+
+        assert_eq!(
+            recv.read_packet(&mut &data[..]),
+            Err(TransportError::Packet(PacketError::InvalidMac))
+        );
+    }
+
+    fn echo_through_server(encryption: CipherType, mac: Mac) {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || run_server(server, encryption, mac, None));
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+
+        assert_eq!(
+            transport.server_identification().software_version(),
+            "test_server"
+        );
+        assert_eq!(transport.session_id().len(), 32);
+        assert_eq!(
+            Ed25519HostKey::from_blob(transport.host_key())
+                .unwrap()
+                .public_key(),
+            SigningKey::from_bytes(&[9; 32]).verifying_key().to_bytes()
+        );
+
+        // The three key exchange packets in each direction have already been counted
+        assert_eq!(transport.send_sequence_number(), 3);
+        assert_eq!(transport.recv_sequence_number(), 3);
+
+        for payload in [&b"hello"[..], &[0; 1000], b"world"] {
+            transport.send_packet(payload).unwrap();
+            assert_eq!(transport.recv_packet().unwrap(), payload);
+        }
+
+        assert_eq!(transport.send_sequence_number(), 6);
+        assert_eq!(transport.recv_sequence_number(), 6);
+
+        drop(transport);
+        assert_eq!(server.join().unwrap(), Ok(None));
+    }
+
+    #[test]
+    fn handshake_aes_ctr_hmac() {
+        echo_through_server(CipherType::AES128CTR, Mac::HmacSha256);
+    }
+
+    #[test]
+    fn handshake_aes_ctr_etm() {
+        echo_through_server(CipherType::AES256CTR, Mac::HmacSha256Etm);
+    }
+
+    #[test]
+    fn handshake_chacha20_poly1305() {
+        echo_through_server(CipherType::ChaCha20Poly1305, Mac::HmacSha1);
+    }
+
+    #[test]
+    fn rekey_policy_defaults() {
+        let policy = RekeyPolicy::default();
+        assert_eq!(policy.max_bytes, 1 << 30);
+        assert_eq!(policy.max_packets, 1 << 31);
+    }
+
+    #[test]
+    fn rekey_after_packet_limit() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(server, CipherType::AES128CTR, Mac::HmacSha256, None)
+        });
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+        let session_id = transport.session_id().to_vec();
+
+        transport.set_rekey_policy(RekeyPolicy {
+            max_bytes: u64::MAX,
+            max_packets: 2,
+        });
+        assert!(!transport.rekey_due());
+
+        // Both packets are sent before reading anything back, so the echoes are still in flight
+        // when the key exchange starts
+        transport.send_packet(b"one").unwrap();
+        transport.send_packet(b"two").unwrap();
+        assert!(transport.rekey_due());
+
+        transport.send_packet(b"three").unwrap();
+        assert!(!transport.rekey_due());
+
+        // Two data packets and four key exchange packets in each direction
+        assert_eq!(transport.send_sequence_number(), 3 + 3 + 3);
+
+        for payload in [&b"one"[..], b"two", b"three"] {
+            assert_eq!(transport.recv_packet().unwrap(), payload);
+        }
+
+        // The session id stays the exchange hash of the first key exchange
+        assert_eq!(transport.session_id(), session_id);
+
+        drop(transport);
+        assert_eq!(server.join().unwrap(), Ok(None));
+    }
+
+    #[test]
+    fn messages_in_flight_during_rekey_are_kept() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(server, CipherType::AES128CTR, Mac::HmacSha256, None)
+        });
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+
+        let ext_info = ExtInfo {
+            extensions: vec![Extension::ServerSigAlgs(vec![HostKeyAlgorithm::SshEd25519])],
+        }
+        .encode()
+        .unwrap();
+        let mut writer = Writer::new();
+        writer.put_u8(MessageType::ServiceAccept as u8);
+        writer.put_string(b"ssh-userauth");
+        let service_accept = writer.into_bytes();
+
+        // The server echoes both just before sending its KexInit for the new key exchange
+        transport.send_packet(&ext_info).unwrap();
+        transport.send_packet(&service_accept).unwrap();
+        transport.rekey().unwrap();
+
+        assert_eq!(transport.recv_packet().unwrap(), ext_info);
+        assert_eq!(transport.recv_packet().unwrap(), service_accept);
+
+        transport.send_packet(b"hello").unwrap();
+        assert_eq!(transport.recv_packet().unwrap(), b"hello");
+
+        drop(transport);
+        assert_eq!(server.join().unwrap(), Ok(None));
+    }
+
+    #[test]
+    fn rekey_started_by_server() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(
+                server,
+                CipherType::ChaCha20Poly1305,
+                Mac::HmacSha256,
+                Some(1),
+            )
+        });
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+
+        transport.send_packet(b"hello").unwrap();
+        assert_eq!(transport.recv_packet().unwrap(), b"hello");
+        assert_eq!(transport.recv_packet().unwrap(), b"rekeyed");
+
+        transport.send_packet(b"world").unwrap();
+        assert_eq!(transport.recv_packet().unwrap(), b"world");
+
+        drop(transport);
+        assert_eq!(server.join().unwrap(), Ok(None));
+    }
+
+    #[test]
+    fn ignore_packets_are_injected_and_dropped() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(server, CipherType::AES128CTR, Mac::HmacSha256, None)
+        });
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+        transport.set_inject_ignore_packets(true);
+
+        transport.send_packet(b"one").unwrap();
+        transport.send_packet(b"two").unwrap();
+        // An ignore packet went before each of them
+        assert_eq!(transport.send_sequence_number(), 3 + 2 * 2);
+
+        // The server echoes the ignore packets too, but only the real payloads come back out
+        assert_eq!(transport.recv_packet().unwrap(), b"one");
+        assert_eq!(transport.recv_packet().unwrap(), b"two");
+        assert_eq!(transport.recv_sequence_number(), 3 + 2 * 2);
+
+        drop(transport);
+        assert_eq!(server.join().unwrap(), Ok(None));
+    }
+
+    #[test]
+    fn disconnect_is_sent_and_closes_the_transport() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(server, CipherType::ChaCha20Poly1305, Mac::HmacSha256, None)
+        });
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+        transport
+            .disconnect(DisconnectMessageType::ByApplication, "bye")
+            .unwrap();
+
+        let closed = Err(TransportError::Packet(PacketError::Closed));
+        assert_eq!(transport.send_packet(b"hello"), closed);
+        assert_eq!(transport.recv_packet().map(|_| ()), closed);
+        assert_eq!(
+            transport.disconnect(DisconnectMessageType::ByApplication, "again"),
+            closed
+        );
+        // Only the disconnect message was sent after the key exchange
+        assert_eq!(transport.send_sequence_number(), 4);
+
+        assert_eq!(
+            server.join().unwrap(),
+            Ok(Some(DisconnectMessage {
+                reason: DisconnectMessageType::ByApplication,
+                description: "bye".into(),
+                language_tag: "".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn messages_stop_after_disconnect() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(server, CipherType::AES128CTR, Mac::HmacSha256, None)
+        });
+
+        let mut transport = ClientTransport::connect(client).unwrap();
+
+        let disconnect = DisconnectMessage {
+            reason: DisconnectMessageType::ByApplication,
+            description: "bye".into(),
+            language_tag: "".into(),
+        };
+        // The server echoes them all back, including the disconnect message
+        let payloads = [
+            ServiceRequest {
+                service: SSHService::UserAuth,
+            }
+            .encode()
+            .unwrap(),
+            vec![MessageType::Unimplemented as u8],
+            disconnect.encode().unwrap(),
+        ];
+        for payload in &payloads {
+            transport.send_packet(payload).unwrap();
+        }
+
+        let mut messages = transport.messages();
+
+        let message = messages.next().unwrap().unwrap();
+        assert_eq!(message.get_type(), MessageType::ServiceRequest);
+        assert_eq!(message.encode().unwrap(), payloads[0]);
+
+        // A message that can't be decoded doesn't end the stream
+        assert!(matches!(
+            messages.next(),
+            Some(Err(RsshError::Message(MessageError::Decode(
+                DecodeError::UnexpectedEnd { .. }
+            ))))
+        ));
+
+        let message = messages.next().unwrap().unwrap();
+        assert_eq!(message.get_type(), MessageType::Disconnect);
+        assert_eq!(message.encode().unwrap(), payloads[2]);
+
+        // The server has gone away, but the stream ends without trying to read again
+        assert!(messages.next().is_none());
+
+        assert_eq!(server.join().unwrap(), Ok(Some(disconnect)));
+    }
+
+    #[test]
+    fn handshake_without_common_cipher() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            run_server(server, CipherType::AES128CBC, Mac::HmacSha256, None)
+        });
+
+        assert_eq!(
+            ClientTransport::connect(client).err(),
+            Some(TransportError::NoCommonAlgorithm {
+                kind: "encryption".into()
+            })
+        );
+        assert!(server.join().unwrap().is_err());
+    }
+
+    /// Run the start of a handshake as the server, sending `messages` instead of the reply to
+    /// the client's `KexEcdhInit`
+    fn answer_kex_with(stream: LoopbackStream, messages: &[&[u8]]) -> Result<(), TransportError> {
+        let mut reader = BufReader::new(stream.clone());
+        let mut writer = stream;
+        let mut send = DirectionState::new();
+        let mut recv = DirectionState::new();
+
+        writer.write_all(format!("{SERVER_IDENT}\r\n").as_bytes())?;
+        reader.read_line(&mut String::new())?;
+
+        send.write_packet(&mut writer, &KexInitMessage::default_server().encode()?)?;
+        recv.read_packet(&mut reader)?;
+        recv.read_packet(&mut reader)?;
+        for message in messages {
+            send.write_packet(&mut writer, message)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn disconnect_during_handshake() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            // The debug message is skipped and the disconnect ends the key exchange
+            let debug = DebugMessage {
+                always_display: false,
+                message: "giving up".into(),
+                language_tag: String::new(),
+            };
+            let disconnect = DisconnectMessage {
+                reason: DisconnectMessageType::KeyExchangeFailed,
+                description: "no thanks".into(),
+                language_tag: String::new(),
+            };
+            answer_kex_with(
+                server,
+                &[&debug.encode().unwrap(), &disconnect.encode().unwrap()],
+            )
+        });
+
+        assert_eq!(
+            ClientTransport::connect(client).err(),
+            Some(TransportError::Disconnected {
+                reason: DisconnectMessageType::KeyExchangeFailed,
+                description: "no thanks".into()
+            })
+        );
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn unimplemented_during_handshake() {
+        let LoopbackPair { client, server } = LoopbackPair::new();
+        let server = std::thread::spawn(move || {
+            let unimplemented = UnimplementedMessage { sequence_number: 1 };
+            answer_kex_with(server, &[&unimplemented.encode().unwrap()])
+        });
+
+        assert_eq!(
+            ClientTransport::connect(client).err(),
+            Some(TransportError::Unimplemented { sequence_number: 1 })
+        );
+        server.join().unwrap().unwrap();
+    }
+}
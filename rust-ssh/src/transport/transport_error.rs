@@ -0,0 +1,109 @@
+use crate::encryption::CipherError;
+use crate::hostkey::SigError;
+use crate::identification::IdentificationError;
+use crate::kex::KexError;
+use crate::message::disconnect::DisconnectMessageType;
+use crate::message::MessageError;
+use crate::packet::PacketError;
+
+/// Possible errors while running a connection
+#[derive(Debug, Eq, PartialEq)]
+pub enum TransportError {
+    /// Reading from or writing to the underlying stream failed
+    Io { kind: std::io::ErrorKind },
+    /// The other side's identification string was invalid
+    Identification(IdentificationError),
+    /// A binary packet couldn't be read or written
+    Packet(PacketError),
+    /// A message couldn't be encoded or decoded
+    Message(MessageError),
+    /// The key exchange failed
+    Kex(KexError),
+    /// The server's host key or its signature of the exchange hash was invalid
+    HostKey(SigError),
+    /// The negotiated cipher couldn't be set up with the derived keys
+    Cipher(CipherError),
+    /// The client and server have no algorithm in common
+    ///
+    /// kind: which algorithm was being negotiated
+    NoCommonAlgorithm { kind: String },
+    /// The server closed the connection with `SSH_MSG_DISCONNECT` during a key exchange
+    Disconnected {
+        reason: DisconnectMessageType,
+        description: String,
+    },
+    /// The server answered a key exchange packet with `SSH_MSG_UNIMPLEMENTED`
+    ///
+    /// sequence_number: the sequence number of the packet it didn't recognize
+    Unimplemented { sequence_number: u32 },
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io { kind } => write!(f, "I/O error: {kind}"),
+            Self::Identification(e) => write!(f, "Identification error: {e}"),
+            Self::Packet(e) => write!(f, "Packet error: {e}"),
+            Self::Message(e) => write!(f, "Message error: {e}"),
+            Self::Kex(e) => write!(f, "Key exchange error: {e}"),
+            Self::HostKey(e) => write!(f, "Host key error: {e}"),
+            Self::Cipher(e) => write!(f, "Cipher error: {e}"),
+            Self::NoCommonAlgorithm { kind } => write!(f, "No common {kind} algorithm"),
+            Self::Disconnected {
+                reason,
+                description,
+            } => write!(f, "Disconnected by the server ({reason}): {description}"),
+            Self::Unimplemented { sequence_number } => {
+                write!(f, "Packet {sequence_number} is unimplemented by the server")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl From<std::io::Error> for TransportError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io { kind: e.kind() }
+    }
+}
+
+impl From<IdentificationError> for TransportError {
+    fn from(e: IdentificationError) -> Self {
+        Self::Identification(e)
+    }
+}
+
+impl From<PacketError> for TransportError {
+    fn from(e: PacketError) -> Self {
+        match e {
+            // Keep stream errors the same whether or not they happened inside a packet
+            PacketError::ReadError { kind } => Self::Io { kind },
+            e => Self::Packet(e),
+        }
+    }
+}
+
+impl From<MessageError> for TransportError {
+    fn from(e: MessageError) -> Self {
+        Self::Message(e)
+    }
+}
+
+impl From<KexError> for TransportError {
+    fn from(e: KexError) -> Self {
+        Self::Kex(e)
+    }
+}
+
+impl From<SigError> for TransportError {
+    fn from(e: SigError) -> Self {
+        Self::HostKey(e)
+    }
+}
+
+impl From<CipherError> for TransportError {
+    fn from(e: CipherError) -> Self {
+        Self::Cipher(e)
+    }
+}